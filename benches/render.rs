@@ -0,0 +1,43 @@
+// renders the sphere on checker plane scene on a single thread and prints the fastest of a few runs.
+// compare the builds with
+//     cargo bench
+//     cargo bench --features simd
+//     cargo bench --features f32
+use std::time::{Duration, Instant};
+
+use sphere_tracer::distance_fields::build_bvh;
+use sphere_tracer::ray_marching::RayMarcherBuilder;
+use sphere_tracer::render::render;
+use sphere_tracer::scene::SceneFile;
+
+const RUNS: u32 = 5;
+
+fn main() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/sphere_on_checker_plane.json");
+    let scene_file = SceneFile::load(path).unwrap();
+
+    let ray_marcher = RayMarcherBuilder::new(build_bvh(scene_file.scene))
+        .lights(scene_file.lights)
+        .build();
+
+    let mut settings = scene_file.settings;
+    settings.width = 480;
+    settings.height = 270;
+    settings.threads = Some(1);
+
+    let mut best = Duration::MAX;
+
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        render(&ray_marcher, &scene_file.camera, &settings).unwrap();
+        best = best.min(start.elapsed());
+    }
+
+    println!(
+        "sphere on checker plane {}x{} ({}, {}): best of {RUNS} runs {best:?}",
+        settings.width,
+        settings.height,
+        if cfg!(feature = "simd") { "simd" } else { "scalar" },
+        if cfg!(feature = "f32") { "f32" } else { "f64" },
+    );
+}
@@ -0,0 +1,113 @@
+use std::fs;
+use std::path::Path;
+
+use crate::camera::Camera;
+use crate::distance_fields::DistanceField;
+use crate::ray_marching::RayMarcher;
+use crate::real::consts::TAU;
+use crate::real::Real;
+use crate::render::{render_pass, OutputFormat, RenderPass, RenderSettings, RenderStats};
+
+// renders the color pass of every frame with t going from 0 towards 1 into frame_0001.png, frame_0002.png, ...
+// in the directory. the last frame stops short of 1 so looping animations don't show the same frame twice.
+// progress gets the index of the frame and the finished and total tiles of it, the stats of all frames are summed
+pub fn render_animation<F>(
+    mut ray_marcher: RayMarcher,
+    frames: u32,
+    settings: &RenderSettings,
+    directory: &str,
+    frame: F,
+    progress: &(dyn Fn(u32, u32, u32) + Sync),
+) -> Result<RenderStats, String>
+where
+    F: Fn(Real) -> (DistanceField, Camera),
+{
+    fs::create_dir_all(directory).map_err(|err| format!("failed to create the frame directory {directory}: {err}"))?;
+
+    let mut stats = RenderStats::default();
+
+    for index in 0..frames {
+        let t = index as Real / frames as Real;
+        let (scene, camera) = frame(t);
+        ray_marcher.scene = scene;
+
+        let settings = RenderSettings { frame: index, ..settings.clone() };
+
+        let (image, frame_stats) = render_pass(&ray_marcher, &camera, &settings, RenderPass::Color, OutputFormat::Png, false, &|finished, total| {
+            progress(index, finished, total)
+        })?;
+
+        let path = Path::new(directory).join(format!("frame_{:04}.png", index + 1));
+        image.save(&path).map_err(|err| format!("failed to write frame {}: {err}", path.display()))?;
+
+        stats = RenderStats::merge(stats, frame_stats);
+    }
+
+    Ok(stats)
+}
+
+// moves the camera a full turn around the vertical axis through the point it looks at
+pub fn orbit_camera(camera: &Camera, t: Real) -> Camera {
+    let offset = (camera.origin - camera.looking_at).rotate_y(t * TAU);
+
+    Camera {
+        origin: camera.looking_at + offset,
+        ..camera.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::camera::create_camera;
+    use crate::distance_fields::Sphere;
+    use crate::ray_marching::create_ray_marcher;
+    use crate::vec3::Vec3;
+
+    #[test]
+    fn orbit_camera_turns_around_the_point_it_looks_at() {
+        let camera = create_camera(Vec3::new(0., 1., -4.), Vec3::new(0., 1., 0.));
+
+        assert!(orbit_camera(&camera, 0.).origin.approx_eq(&camera.origin, 1e-5));
+        assert!(orbit_camera(&camera, 0.5).origin.approx_eq(&Vec3::new(0., 1., 4.), 1e-5));
+
+        let quarter = orbit_camera(&camera, 0.25);
+        assert!(((quarter.origin - quarter.looking_at).length() - 4.).abs() < 1e-5);
+        assert!(quarter.looking_at.approx_eq(&camera.looking_at, 1e-6));
+    }
+
+    #[test]
+    fn render_animation_writes_every_frame() {
+        let sphere = DistanceField::Sphere(Sphere { pos: Vec3::zero(), size: 1. });
+        let camera = create_camera(Vec3::new(0., 0., -5.), Vec3::zero());
+        let settings = RenderSettings { width: 16, height: 12, threads: Some(2), ..RenderSettings::default() };
+
+        let directory = std::env::temp_dir().join("sphere_tracer_animation_frames");
+        let _ = fs::remove_dir_all(&directory);
+        let frames_started = AtomicU32::new(0);
+
+        let stats = render_animation(
+            create_ray_marcher(sphere.clone()),
+            3,
+            &settings,
+            directory.to_str().unwrap(),
+            |t| (sphere.clone(), orbit_camera(&camera, t)),
+            &|frame, finished, _| {
+                if finished == 1 {
+                    assert_eq!(frames_started.fetch_add(1, Ordering::Relaxed), frame);
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(frames_started.load(Ordering::Relaxed), 3);
+        assert_eq!(stats.rays, 3 * 16 * 12);
+
+        for index in 1..=3 {
+            let frame = image::open(directory.join(format!("frame_{index:04}.png"))).unwrap();
+            assert_eq!((frame.width(), frame.height()), (16, 12));
+        }
+    }
+}
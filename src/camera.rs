@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+
+use crate::mat4::Mat4;
+use crate::ray::Ray;
+use crate::real::{consts, Real};
+use crate::rng::Rng;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Projection {
+    // the rays start at the origin and spread out through the view port
+    #[default]
+    Perspective,
+    // parallel rays along the view direction that start on the view port, which is viewport_height
+    // high in world units and centered at the origin. focal_length and the depth of field don't apply
+    Orthographic,
+    // a full equirectangular panorama around the origin, the image has to have an aspect ratio of 2:1. u is the
+    // longitude and v the latitude. the view direction is at the center, the view port and the depth of field don't apply
+    Panoramic,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Camera {
+    pub origin: Vec3,
+    pub looking_at: Vec3,
+    // the direction that is up in the image, scene files without it use the world up
+    #[serde(default = "world_up")]
+    pub up: Vec3,
+    #[serde(default)]
+    pub projection: Projection,
+
+    // view port
+    pub viewport_height: Real,
+    pub focal_length: Real,
+
+    // depth of field
+    pub aperture: Real,
+    pub focus_distance: Real,
+}
+
+fn world_up() -> Vec3 {
+    Vec3::new(0., 1., 0.)
+}
+
+pub fn create_camera(origin: Vec3, looking_at: Vec3) -> Camera {
+    let viewport_height = 2.;
+    let focal_length = 3.;
+
+    let aperture = 0.;
+    let focus_distance = (looking_at - origin).length();
+
+    Camera {
+        origin,
+        looking_at,
+        up: world_up(),
+        projection: Projection::Perspective,
+
+        viewport_height,
+        focal_length,
+
+        aperture,
+        focus_distance,
+    }
+}
+
+impl Camera {
+    // the up vector, or a world axis when the view direction is (nearly) parallel to it, since the
+    // cross product of the two would be zero and the view port would collapse
+    fn up_for(&self, view_direction: &Vec3) -> Vec3 {
+        let up = self.up.normalize();
+
+        if Vec3::cross(view_direction, &up).length() > 1e-6 {
+            return up;
+        }
+
+        // looking straight up or down the top of the image points along -z, otherwise along y
+        if view_direction.y.abs() > 0.5 {
+            Vec3::new(0., 0., -1.)
+        } else {
+            world_up()
+        }
+    }
+
+    // the angle a pixel covers at the center of the image, for an image that is height pixels high.
+    // the pixels of an orthographic camera don't grow with the distance, so it's 0 there
+    pub fn pixel_angle(&self, height: u32) -> Real {
+        match self.projection {
+            Projection::Perspective => self.viewport_height / (self.focal_length * height as Real),
+            Projection::Orthographic => 0.,
+            Projection::Panoramic => consts::PI / height as Real,
+        }
+    }
+
+    // from camera space into world space. the camera sits at the origin of camera space and looks along z,
+    // x points to the right of the image and y to the top
+    pub fn view_matrix(&self) -> Mat4 {
+        let forward = (self.looking_at - self.origin).normalize();
+        let right = Vec3::cross(&forward, &self.up_for(&forward)).normalize();
+        let up = Vec3::cross(&right, &forward);
+
+        Mat4::from_basis(&right, &up, &forward, &self.origin)
+    }
+
+    pub fn ray_for_pixel(&self, u: Real, v: Real, aspect_ratio: Real, rng: &mut Rng) -> Ray {
+        let view = self.view_matrix();
+
+        let viewport_width = aspect_ratio * self.viewport_height;
+        // the point of the view port in camera space, the view port is centered around the view direction
+        let x = (u - 0.5) * viewport_width;
+        let y = (v - 0.5) * self.viewport_height;
+
+        let pixel_dir = match self.projection {
+            Projection::Perspective => Vec3::new(x, y, self.focal_length),
+            Projection::Orthographic => {
+                return Ray::new(&view.transform_point(&Vec3::new(x, y, 0.)), &view.transform_vector(&Vec3::new(0., 0., 1.)));
+            }
+            Projection::Panoramic => {
+                let longitude = (u - 0.5) * consts::TAU;
+                let latitude = (v - 0.5) * consts::PI;
+
+                let dir = Vec3::new(latitude.cos() * longitude.sin(), latitude.sin(), latitude.cos() * longitude.cos());
+                return Ray::new(&self.origin, &view.transform_vector(&dir));
+            }
+        };
+
+        // a pinhole camera, everything is in focus
+        if self.aperture <= 0. {
+            return Ray::new(&self.origin, &view.transform_vector(&pixel_dir));
+        }
+
+        // the point on the focal plane this pixel looks at
+        let focus_point = pixel_dir * (self.focus_distance / self.focal_length);
+
+        // jitter the ray origin on a disk of the lens, which lies in the xy plane of the camera
+        let (lens_x, lens_y) = rng.in_unit_disk();
+        let lens_origin = Vec3::new(lens_x, lens_y, 0.) * (self.aperture / 2.);
+
+        Ray::new(&view.transform_point(&lens_origin), &view.transform_vector(&(focus_point - lens_origin)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_finite(v: &Vec3) -> bool {
+        v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+    }
+
+    #[test]
+    fn looking_straight_down_has_a_valid_basis() {
+        for looking_at in [Vec3::new(0., 0., 0.), Vec3::new(0., 10., 0.)] {
+            let camera = create_camera(Vec3::new(0., 5., 0.), looking_at);
+            let view = camera.view_matrix();
+
+            let right = view.transform_vector(&Vec3::new(1., 0., 0.));
+            let up = view.transform_vector(&Vec3::new(0., 1., 0.));
+            let forward = view.transform_vector(&Vec3::new(0., 0., 1.));
+
+            for axis in [&right, &up, &forward] {
+                assert!(is_finite(axis));
+                assert!((axis.length() - 1.).abs() < 1e-6);
+            }
+            assert!(Vec3::dot(&right, &up).abs() < 1e-6);
+            assert!(Vec3::dot(&right, &forward).abs() < 1e-6);
+            assert!(Vec3::dot(&up, &forward).abs() < 1e-6);
+
+            let mut rng = Rng::new(0);
+            for (u, v) in [(0., 0.), (0.5, 0.5), (1., 1.), (0.2, 0.9)] {
+                assert!(is_finite(&camera.ray_for_pixel(u, v, 16. / 9., &mut rng).dir));
+            }
+
+            let center = camera.ray_for_pixel(0.5, 0.5, 16. / 9., &mut rng);
+            assert!(center.dir.approx_eq(&(looking_at - camera.origin).normalize(), 1e-6));
+        }
+    }
+
+    #[test]
+    fn up_vector_points_to_the_top_of_the_image() {
+        let mut camera = create_camera(Vec3::new(0., 0., 5.), Vec3::new(0., 0., 0.));
+        camera.up = Vec3::new(1., 0., 0.);
+        let mut rng = Rng::new(0);
+
+        let top = camera.ray_for_pixel(0.5, 1., 1., &mut rng);
+        let bottom = camera.ray_for_pixel(0.5, 0., 1., &mut rng);
+
+        assert!(top.dir.x > 0. && bottom.dir.x < 0.);
+        assert!(top.dir.y.abs() < 1e-6);
+    }
+
+    #[test]
+    fn panoramic_center_looks_forward() {
+        let mut camera = create_camera(Vec3::new(1., 2., 3.), Vec3::new(4., 2., -1.));
+        camera.projection = Projection::Panoramic;
+        let mut rng = Rng::new(0);
+        let forward = (camera.looking_at - camera.origin).normalize();
+
+        let center = camera.ray_for_pixel(0.5, 0.5, 2., &mut rng);
+        assert!(center.orig.approx_eq(&camera.origin, 0.));
+        assert!(center.dir.approx_eq(&forward, 1e-6));
+
+        // the left and right edges both look backwards, the top and bottom straight up and down
+        assert!(camera.ray_for_pixel(0., 0.5, 2., &mut rng).dir.approx_eq(&-forward, 1e-6));
+        assert!(camera.ray_for_pixel(1., 0.5, 2., &mut rng).dir.approx_eq(&-forward, 1e-6));
+        assert!(camera.ray_for_pixel(0.5, 1., 2., &mut rng).dir.approx_eq(&Vec3::new(0., 1., 0.), 1e-6));
+        assert!(camera.ray_for_pixel(0.5, 0., 2., &mut rng).dir.approx_eq(&Vec3::new(0., -1., 0.), 1e-6));
+        // a quarter turn to the right
+        let right = Vec3::cross(&forward, &Vec3::new(0., 1., 0.));
+        assert!(camera.ray_for_pixel(0.75, 0.5, 2., &mut rng).dir.approx_eq(&right, 1e-6));
+    }
+}
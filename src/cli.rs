@@ -0,0 +1,129 @@
+use std::str::FromStr;
+
+use sphere_tracer::render::{DenoiseSettings, OutputFormat, RenderSettings};
+
+// the render settings are only set when given, so they can override the ones from a scene file
+pub struct CliArgs {
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub samples: Option<u32>,
+    pub seed: Option<u64>,
+    pub threads: Option<usize>,
+    pub background_blocks: Option<u32>,
+    // defaults to out.png or out.exr depending on the format
+    pub output: Option<String>,
+    pub format: OutputFormat,
+    // png with an alpha channel that is transparent where the rays missed
+    pub png_alpha: bool,
+    // hit surfaces once they are smaller than a pixel, saves iterations on far away geometry
+    pub cone_tracing: bool,
+    pub denoise: bool,
+    pub hemisphere_ambient: bool,
+    pub gi_samples: Option<u32>,
+    // number of frames of a camera orbit, --output is the directory for the frames then
+    pub animate: Option<u32>,
+    pub scene: Option<String>,
+    // optional passes written next to the color image
+    pub depth: Option<String>,
+    pub normal: Option<String>,
+    pub heatmap: Option<String>,
+    pub edges: Option<String>,
+}
+
+pub fn parse_args(mut args: impl Iterator<Item = String>) -> Result<CliArgs, String> {
+    let mut cli_args = CliArgs {
+        width: None,
+        height: None,
+        samples: None,
+        seed: None,
+        threads: None,
+        background_blocks: None,
+        output: None,
+        format: OutputFormat::Png,
+        png_alpha: false,
+        cone_tracing: false,
+        denoise: false,
+        hemisphere_ambient: false,
+        gi_samples: None,
+        animate: None,
+        scene: None,
+        depth: None,
+        normal: None,
+        heatmap: None,
+        edges: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--width" => cli_args.width = Some(parse_value(&arg, args.next())?),
+            "--height" => cli_args.height = Some(parse_value(&arg, args.next())?),
+            "--samples" => cli_args.samples = Some(parse_value(&arg, args.next())?),
+            "--seed" => cli_args.seed = Some(parse_value(&arg, args.next())?),
+            "--threads" => cli_args.threads = Some(parse_value(&arg, args.next())?),
+            "--background-blocks" => cli_args.background_blocks = Some(parse_value(&arg, args.next())?),
+            "--output" => cli_args.output = Some(parse_value(&arg, args.next())?),
+            "--format" => cli_args.format = parse_value(&arg, args.next())?,
+            "--png-alpha" => cli_args.png_alpha = true,
+            "--cone-tracing" => cli_args.cone_tracing = true,
+            "--denoise" => cli_args.denoise = true,
+            "--hemisphere-ambient" => cli_args.hemisphere_ambient = true,
+            "--gi-samples" => cli_args.gi_samples = Some(parse_value(&arg, args.next())?),
+            "--animate" => cli_args.animate = Some(parse_value(&arg, args.next())?),
+            "--scene" => cli_args.scene = Some(parse_value(&arg, args.next())?),
+            "--depth" => cli_args.depth = Some(parse_value(&arg, args.next())?),
+            "--normal" => cli_args.normal = Some(parse_value(&arg, args.next())?),
+            "--heatmap" => cli_args.heatmap = Some(parse_value(&arg, args.next())?),
+            "--edges" => cli_args.edges = Some(parse_value(&arg, args.next())?),
+            _ => return Err(format!("unknown argument '{arg}'")),
+        }
+    }
+
+    if cli_args.png_alpha && cli_args.format != OutputFormat::Png {
+        return Err(String::from("--png-alpha only works with the png format"));
+    }
+
+    if cli_args.animate == Some(0) {
+        return Err(String::from("animate needs at least 1 frame"));
+    }
+
+    Ok(cli_args)
+}
+
+impl CliArgs {
+    pub fn apply(&self, settings: &mut RenderSettings) {
+        if let Some(width) = self.width {
+            settings.width = width;
+        }
+
+        if let Some(height) = self.height {
+            settings.height = height;
+        }
+
+        if let Some(samples) = self.samples {
+            settings.samples = samples;
+        }
+
+        if let Some(seed) = self.seed {
+            settings.seed = seed;
+        }
+
+        if self.threads.is_some() {
+            settings.threads = self.threads;
+        }
+
+        if self.background_blocks.is_some() {
+            settings.background_blocks = self.background_blocks;
+        }
+
+        // the sigmas of the scene file are kept
+        if self.denoise && settings.denoise.is_none() {
+            settings.denoise = Some(DenoiseSettings::default());
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(arg: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("missing value for '{arg}'"))?;
+
+    value.parse().map_err(|_| format!("invalid value '{value}' for '{arg}'"))
+}
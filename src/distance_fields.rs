@@ -1,214 +1,2220 @@
-use num::complex::ComplexFloat;
+use std::fmt;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::mat4::Mat4;
+use crate::material::Material;
+use crate::noise::fbm;
+use crate::quaternion::Quaternion;
+use crate::real::{consts, narrow, widen, Real};
+use crate::vec2::Vec2;
 use crate::vec3::Vec3;
 use crate::vec4::Vec4;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DistanceField {
     Sphere(Sphere),
     Cuboid(Cuboid),
     Torus(Torus),
+    CappedTorus(CappedTorus),
+    Link(Link),
     Plane(Plane),
+    CheckerPlane(CheckerPlane),
+    Heightfield(Heightfield),
+    Disc(Disc),
+    Quad(Quad),
+    Capsule(Capsule),
+    Cone(Cone),
+    Ellipsoid(Ellipsoid),
+    Octahedron(Octahedron),
+    HexPrism(HexPrism),
+    RoundBox(RoundBox),
+    BoxFrame(BoxFrame),
     Julia(Julia),
+    Mandelbulb(Mandelbulb),
+    Mandelbox(Mandelbox),
+    Menger(Menger),
     Union(Box<Union>),
+    SmoothUnion(Box<SmoothUnion>),
     Subtraction(Box<Subtraction>),
+    SmoothSubtraction(Box<SmoothSubtraction>),
     Intersection(Box<Intersection>),
+    SmoothIntersection(Box<SmoothIntersection>),
+    Repeat(Box<Repeat>),
+    RepeatLimited(Box<RepeatLimited>),
+    RepeatMirror(Box<RepeatMirror>),
+    Transform(Box<Transform>),
+    Scale(Box<Scale>),
+    Twist(Box<Twist>),
+    Elongate(Box<Elongate>),
+    Onion(Box<Onion>),
+    Round(Box<Round>),
+    Displace(Box<Displace>),
+    WithMaterial(Box<WithMaterial>),
+    Bounded(Box<Bounded>),
+    Bvh(Box<Bvh>),
+    #[serde(skip)]
+    Custom(Custom),
 }
 
+// the renderer shares the scene between its threads, so a field that isn't Send + Sync fails to compile here
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<DistanceField>();
+};
+
 pub trait DistanceFunction {
-    fn get_distance(&self, p: &Vec3) -> f64;
+    fn get_distance(&self, p: &Vec3) -> Real;
+
+    // the material of the surface closest to p, None if it should use the default material
+    fn get_material(&self, _p: &Vec3) -> Option<Material> {
+        None
+    }
+
+    // the exact surface normal at p, None if it has to be estimated from the distance field
+    fn get_normal_analytic(&self, _p: &Vec3) -> Option<Vec3> {
+        None
+    }
+
+    // axis aligned bounding box (min, max) that contains the whole surface, None if it's unbounded or unknown
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        None
+    }
 }
 
 impl DistanceFunction for DistanceField {
-    fn get_distance(&self, p: &Vec3) -> f64 {
+    fn get_distance(&self, p: &Vec3) -> Real {
         match self {
             DistanceField::Sphere(x) => x.get_distance(p),
             DistanceField::Cuboid(x) => x.get_distance(p),
             DistanceField::Torus(x) => x.get_distance(p),
+            DistanceField::CappedTorus(x) => x.get_distance(p),
+            DistanceField::Link(x) => x.get_distance(p),
             DistanceField::Plane(x) => x.get_distance(p),
+            DistanceField::CheckerPlane(x) => x.get_distance(p),
+            DistanceField::Heightfield(x) => x.get_distance(p),
+            DistanceField::Disc(x) => x.get_distance(p),
+            DistanceField::Quad(x) => x.get_distance(p),
+            DistanceField::Capsule(x) => x.get_distance(p),
+            DistanceField::Cone(x) => x.get_distance(p),
+            DistanceField::Ellipsoid(x) => x.get_distance(p),
+            DistanceField::Octahedron(x) => x.get_distance(p),
+            DistanceField::HexPrism(x) => x.get_distance(p),
+            DistanceField::RoundBox(x) => x.get_distance(p),
+            DistanceField::BoxFrame(x) => x.get_distance(p),
             DistanceField::Julia(x) => x.get_distance(p),
+            DistanceField::Mandelbulb(x) => x.get_distance(p),
+            DistanceField::Mandelbox(x) => x.get_distance(p),
+            DistanceField::Menger(x) => x.get_distance(p),
             DistanceField::Union(x) => x.get_distance(p),
+            DistanceField::SmoothUnion(x) => x.get_distance(p),
             DistanceField::Subtraction(x) => x.get_distance(p),
+            DistanceField::SmoothSubtraction(x) => x.get_distance(p),
             DistanceField::Intersection(x) => x.get_distance(p),
+            DistanceField::SmoothIntersection(x) => x.get_distance(p),
+            DistanceField::Repeat(x) => x.get_distance(p),
+            DistanceField::RepeatLimited(x) => x.get_distance(p),
+            DistanceField::RepeatMirror(x) => x.get_distance(p),
+            DistanceField::Transform(x) => x.get_distance(p),
+            DistanceField::Scale(x) => x.get_distance(p),
+            DistanceField::Twist(x) => x.get_distance(p),
+            DistanceField::Elongate(x) => x.get_distance(p),
+            DistanceField::Onion(x) => x.get_distance(p),
+            DistanceField::Round(x) => x.get_distance(p),
+            DistanceField::Displace(x) => x.get_distance(p),
+            DistanceField::WithMaterial(x) => x.get_distance(p),
+            DistanceField::Bounded(x) => x.get_distance(p),
+            DistanceField::Bvh(x) => x.get_distance(p),
+            DistanceField::Custom(x) => x.get_distance(p),
+        }
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        match self {
+            DistanceField::Sphere(x) => x.get_material(p),
+            DistanceField::Cuboid(x) => x.get_material(p),
+            DistanceField::Torus(x) => x.get_material(p),
+            DistanceField::CappedTorus(x) => x.get_material(p),
+            DistanceField::Link(x) => x.get_material(p),
+            DistanceField::Plane(x) => x.get_material(p),
+            DistanceField::CheckerPlane(x) => x.get_material(p),
+            DistanceField::Heightfield(x) => x.get_material(p),
+            DistanceField::Disc(x) => x.get_material(p),
+            DistanceField::Quad(x) => x.get_material(p),
+            DistanceField::Capsule(x) => x.get_material(p),
+            DistanceField::Cone(x) => x.get_material(p),
+            DistanceField::Ellipsoid(x) => x.get_material(p),
+            DistanceField::Octahedron(x) => x.get_material(p),
+            DistanceField::HexPrism(x) => x.get_material(p),
+            DistanceField::RoundBox(x) => x.get_material(p),
+            DistanceField::BoxFrame(x) => x.get_material(p),
+            DistanceField::Julia(x) => x.get_material(p),
+            DistanceField::Mandelbulb(x) => x.get_material(p),
+            DistanceField::Mandelbox(x) => x.get_material(p),
+            DistanceField::Menger(x) => x.get_material(p),
+            DistanceField::Union(x) => x.get_material(p),
+            DistanceField::SmoothUnion(x) => x.get_material(p),
+            DistanceField::Subtraction(x) => x.get_material(p),
+            DistanceField::SmoothSubtraction(x) => x.get_material(p),
+            DistanceField::Intersection(x) => x.get_material(p),
+            DistanceField::SmoothIntersection(x) => x.get_material(p),
+            DistanceField::Repeat(x) => x.get_material(p),
+            DistanceField::RepeatLimited(x) => x.get_material(p),
+            DistanceField::RepeatMirror(x) => x.get_material(p),
+            DistanceField::Transform(x) => x.get_material(p),
+            DistanceField::Scale(x) => x.get_material(p),
+            DistanceField::Twist(x) => x.get_material(p),
+            DistanceField::Elongate(x) => x.get_material(p),
+            DistanceField::Onion(x) => x.get_material(p),
+            DistanceField::Round(x) => x.get_material(p),
+            DistanceField::Displace(x) => x.get_material(p),
+            DistanceField::WithMaterial(x) => x.get_material(p),
+            DistanceField::Bounded(x) => x.get_material(p),
+            DistanceField::Bvh(x) => x.get_material(p),
+            DistanceField::Custom(x) => x.get_material(p),
+        }
+    }
+    fn get_normal_analytic(&self, p: &Vec3) -> Option<Vec3> {
+        match self {
+            DistanceField::Sphere(x) => x.get_normal_analytic(p),
+            DistanceField::Cuboid(x) => x.get_normal_analytic(p),
+            DistanceField::Torus(x) => x.get_normal_analytic(p),
+            DistanceField::CappedTorus(x) => x.get_normal_analytic(p),
+            DistanceField::Link(x) => x.get_normal_analytic(p),
+            DistanceField::Plane(x) => x.get_normal_analytic(p),
+            DistanceField::CheckerPlane(x) => x.get_normal_analytic(p),
+            DistanceField::Heightfield(x) => x.get_normal_analytic(p),
+            DistanceField::Disc(x) => x.get_normal_analytic(p),
+            DistanceField::Quad(x) => x.get_normal_analytic(p),
+            DistanceField::Capsule(x) => x.get_normal_analytic(p),
+            DistanceField::Cone(x) => x.get_normal_analytic(p),
+            DistanceField::Ellipsoid(x) => x.get_normal_analytic(p),
+            DistanceField::Octahedron(x) => x.get_normal_analytic(p),
+            DistanceField::HexPrism(x) => x.get_normal_analytic(p),
+            DistanceField::RoundBox(x) => x.get_normal_analytic(p),
+            DistanceField::BoxFrame(x) => x.get_normal_analytic(p),
+            DistanceField::Julia(x) => x.get_normal_analytic(p),
+            DistanceField::Mandelbulb(x) => x.get_normal_analytic(p),
+            DistanceField::Mandelbox(x) => x.get_normal_analytic(p),
+            DistanceField::Menger(x) => x.get_normal_analytic(p),
+            DistanceField::Union(x) => x.get_normal_analytic(p),
+            DistanceField::SmoothUnion(x) => x.get_normal_analytic(p),
+            DistanceField::Subtraction(x) => x.get_normal_analytic(p),
+            DistanceField::SmoothSubtraction(x) => x.get_normal_analytic(p),
+            DistanceField::Intersection(x) => x.get_normal_analytic(p),
+            DistanceField::SmoothIntersection(x) => x.get_normal_analytic(p),
+            DistanceField::Repeat(x) => x.get_normal_analytic(p),
+            DistanceField::RepeatLimited(x) => x.get_normal_analytic(p),
+            DistanceField::RepeatMirror(x) => x.get_normal_analytic(p),
+            DistanceField::Transform(x) => x.get_normal_analytic(p),
+            DistanceField::Scale(x) => x.get_normal_analytic(p),
+            DistanceField::Twist(x) => x.get_normal_analytic(p),
+            DistanceField::Elongate(x) => x.get_normal_analytic(p),
+            DistanceField::Onion(x) => x.get_normal_analytic(p),
+            DistanceField::Round(x) => x.get_normal_analytic(p),
+            DistanceField::Displace(x) => x.get_normal_analytic(p),
+            DistanceField::WithMaterial(x) => x.get_normal_analytic(p),
+            DistanceField::Bounded(x) => x.get_normal_analytic(p),
+            DistanceField::Bvh(x) => x.get_normal_analytic(p),
+            DistanceField::Custom(x) => x.get_normal_analytic(p),
+        }
+    }
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        match self {
+            DistanceField::Sphere(x) => x.bounds(),
+            DistanceField::Cuboid(x) => x.bounds(),
+            DistanceField::Torus(x) => x.bounds(),
+            DistanceField::CappedTorus(x) => x.bounds(),
+            DistanceField::Link(x) => x.bounds(),
+            DistanceField::Plane(x) => x.bounds(),
+            DistanceField::CheckerPlane(x) => x.bounds(),
+            DistanceField::Heightfield(x) => x.bounds(),
+            DistanceField::Disc(x) => x.bounds(),
+            DistanceField::Quad(x) => x.bounds(),
+            DistanceField::Capsule(x) => x.bounds(),
+            DistanceField::Cone(x) => x.bounds(),
+            DistanceField::Ellipsoid(x) => x.bounds(),
+            DistanceField::Octahedron(x) => x.bounds(),
+            DistanceField::HexPrism(x) => x.bounds(),
+            DistanceField::RoundBox(x) => x.bounds(),
+            DistanceField::BoxFrame(x) => x.bounds(),
+            DistanceField::Julia(x) => x.bounds(),
+            DistanceField::Mandelbulb(x) => x.bounds(),
+            DistanceField::Mandelbox(x) => x.bounds(),
+            DistanceField::Menger(x) => x.bounds(),
+            DistanceField::Union(x) => x.bounds(),
+            DistanceField::SmoothUnion(x) => x.bounds(),
+            DistanceField::Subtraction(x) => x.bounds(),
+            DistanceField::SmoothSubtraction(x) => x.bounds(),
+            DistanceField::Intersection(x) => x.bounds(),
+            DistanceField::SmoothIntersection(x) => x.bounds(),
+            DistanceField::Repeat(x) => x.bounds(),
+            DistanceField::RepeatLimited(x) => x.bounds(),
+            DistanceField::RepeatMirror(x) => x.bounds(),
+            DistanceField::Transform(x) => x.bounds(),
+            DistanceField::Scale(x) => x.bounds(),
+            DistanceField::Twist(x) => x.bounds(),
+            DistanceField::Elongate(x) => x.bounds(),
+            DistanceField::Onion(x) => x.bounds(),
+            DistanceField::Round(x) => x.bounds(),
+            DistanceField::Displace(x) => x.bounds(),
+            DistanceField::WithMaterial(x) => x.bounds(),
+            DistanceField::Bounded(x) => x.bounds(),
+            DistanceField::Bvh(x) => x.bounds(),
+            DistanceField::Custom(x) => x.bounds(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Sphere {
     pub pos: Vec3,
-    pub size: f64,
+    pub size: Real,
 }
 
 impl DistanceFunction for Sphere {
-    fn get_distance(&self, p: &Vec3) -> f64 {
+    fn get_distance(&self, p: &Vec3) -> Real {
         (p - self.pos).length() - self.size
     }
+
+    fn get_normal_analytic(&self, p: &Vec3) -> Option<Vec3> {
+        Some((p - self.pos).normalize())
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.pos - self.size, self.pos + self.size))
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cuboid {
     pub pos: Vec3,
     pub size: Vec3,
 }
 
 impl DistanceFunction for Cuboid {
-    fn get_distance(&self, p: &Vec3) -> f64 {
+    fn get_distance(&self, p: &Vec3) -> Real {
         let q = (p - self.pos).abs() - self.size;
-        (Vec3::max(&q, 0.) + q.max_element().min(0.)).length()
+        Vec3::max(&q, 0.).length() + q.max_element().min(0.)
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.pos - self.size, self.pos + self.size))
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Torus {
     pub pos: Vec3,
-    pub outer_size: f64,
-    pub inner_size: f64,
+    // the direction the hole of the torus points to, (0, 1, 0) lays it flat in the xz plane
+    pub axis: Vec3,
+    pub outer_size: Real,
+    pub inner_size: Real,
+}
+
+impl Torus {
+    // splits the point relative to the center into the height along the axis and the offset in the ring plane
+    fn local_point(&self, p: &Vec3) -> (Real, Vec3) {
+        let axis = self.axis.normalize();
+        let q = p - self.pos;
+        let height = Vec3::dot(&q, &axis);
+
+        (height, q - axis * height)
+    }
 }
 
 impl DistanceFunction for Torus {
-    fn get_distance(&self, p: &Vec3) -> f64 {
-        let q = Vec3::new((p.x * p.x + p.z * p.z).sqrt() - self.outer_size, p.y, 0.);
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let (height, radial) = self.local_point(p);
+        let q = Vec2::new(radial.length() - self.outer_size, height);
         q.length() - self.inner_size
     }
+
+    fn get_normal_analytic(&self, p: &Vec3) -> Option<Vec3> {
+        // the normal points away from the closest point on the center ring
+        let (_, radial) = self.local_point(p);
+        let ring_point = self.pos + radial.normalize() * self.outer_size;
+        Some((p - ring_point).normalize())
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        // the ring reaches outer_size * sin(angle between axis and world axis) along each world axis
+        let axis = self.axis.normalize();
+        let ring_extent = Vec3::new(
+            (1. - axis.x * axis.x).max(0.).sqrt(),
+            (1. - axis.y * axis.y).max(0.).sqrt(),
+            (1. - axis.z * axis.z).max(0.).sqrt(),
+        ) * self.outer_size;
+        let extent = ring_extent + self.inner_size;
+        Some((self.pos - extent, self.pos + extent))
+    }
+}
+
+// capped torus https://iquilezles.org/articles/distfunctions
+// an arc of a torus in the xy plane around pos, cap holds (sin, cos) of the half angle of the arc measured
+// from the +y axis, a half angle of pi gives the full torus
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CappedTorus {
+    pub pos: Vec3,
+    pub outer_size: Real,
+    pub inner_size: Real,
+    pub cap: (Real, Real),
+}
+
+impl DistanceFunction for CappedTorus {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let mut q = p - self.pos;
+        q.x = q.x.abs();
+
+        let (sin, cos) = self.cap;
+
+        // past the end of the arc the closest ring point is the end point of the arc
+        let k = if cos * q.x > sin * q.y {
+            q.x * sin + q.y * cos
+        } else {
+            (q.x * q.x + q.y * q.y).sqrt()
+        };
+
+        (q.sqr_length() + self.outer_size * self.outer_size - 2. * self.outer_size * k).max(0.).sqrt() - self.inner_size
+    }
+}
+
+// chain link https://iquilezles.org/articles/distfunctions
+// a torus in the xy plane that is stretched along y, length is the distance between the centers of the round ends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Link {
+    pub pos: Vec3,
+    pub length: Real,
+    pub outer_size: Real,
+    pub inner_size: Real,
+}
+
+impl DistanceFunction for Link {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let p = p - self.pos;
+        let q = Vec3::new(p.x, (p.y.abs() - self.length / 2.).max(0.), p.z);
+
+        Vec2::new(Vec2::new(q.x, q.y).length() - self.outer_size, q.z).length() - self.inner_size
+    }
 }
 
-#[derive(Debug, Clone)]
+// the plane contains the points with dot(p, normal / |normal|) = -h, so h is the offset along the unit normal
+// whatever the length of the normal. the distance is positive on the side the normal points to
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Plane {
     pub normal: Vec3,
-    pub h: f64,
+    pub h: Real,
 }
 
 impl DistanceFunction for Plane {
-    fn get_distance(&self, p: &Vec3) -> f64 {
-        Vec3::dot(p, &self.normal) + self.h
+    fn get_distance(&self, p: &Vec3) -> Real {
+        Vec3::dot(p, &self.normal.normalize()) + self.h
+    }
+
+    fn get_normal_analytic(&self, _p: &Vec3) -> Option<Vec3> {
+        Some(self.normal.normalize())
+    }
+}
+
+// a plane with a checkerboard pattern of tiles that are scale wide along the tangent directions of the plane
+// (x and z for a floor with the normal (0, 1, 0)), normal and h work like on Plane
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckerPlane {
+    pub normal: Vec3,
+    pub h: Real,
+    pub scale: Real,
+    pub color_a: Vec3,
+    pub color_b: Vec3,
+}
+
+impl DistanceFunction for CheckerPlane {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        Vec3::dot(p, &self.normal.normalize()) + self.h
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        let (tangent, bitangent) = tangent_basis(&self.normal.normalize());
+        let parity = (Vec3::dot(p, &tangent) / self.scale).floor() + (Vec3::dot(p, &bitangent) / self.scale).floor();
+
+        let color = if parity.rem_euclid(2.) == 0. {
+            self.color_a
+        } else {
+            self.color_b
+        };
+
+        Some(Material::new(color))
+    }
+
+    fn get_normal_analytic(&self, _p: &Vec3) -> Option<Vec3> {
+        Some(self.normal.normalize())
+    }
+}
+
+// procedural terrain, the ground at height h displaced by amplitude times fbm noise of the xz position.
+// the vertical distance to the ground isn't the real distance on slopes, so this is only an approximation
+// that's scaled by the lipschitz factor k < 1. lower k if rays overstep the surface (holes or noise on
+// steep slopes), the price is more iterations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Heightfield {
+    pub h: Real,
+    pub amplitude: Real,
+    pub frequency: Real,
+    pub octaves: u32,
+    pub lacunarity: Real,
+    pub gain: Real,
+    pub k: Real,
+}
+
+impl Heightfield {
+    pub fn height(&self, x: Real, z: Real) -> Real {
+        self.h + self.amplitude * fbm(&Vec3::new(x * self.frequency, 0., z * self.frequency), self.octaves, self.lacunarity, self.gain)
+    }
+}
+
+impl DistanceFunction for Heightfield {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        (p.y - self.height(p.x, p.z)) * self.k
+    }
+}
+
+// a flat disc with no thickness, it lies in the plane through pos perpendicular to normal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Disc {
+    pub pos: Vec3,
+    pub normal: Vec3,
+    pub radius: Real,
+}
+
+impl DistanceFunction for Disc {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let (height, radial) = plane_coordinates(&(p - self.pos), &self.normal);
+
+        // distance to the closest point of the disc, which is the projection clamped to the rim
+        let outside = (radial.length() - self.radius).max(0.);
+        (height * height + outside * outside).sqrt()
+    }
+}
+
+// a flat rectangle with no thickness, half_width and half_length are measured along the tangent
+// directions of the plane (x and z for a floor with the normal (0, 1, 0))
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quad {
+    pub pos: Vec3,
+    pub normal: Vec3,
+    pub half_width: Real,
+    pub half_length: Real,
+}
+
+impl DistanceFunction for Quad {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let normal = self.normal.normalize();
+        let (height, radial) = plane_coordinates(&(p - self.pos), &normal);
+
+        let (tangent, bitangent) = tangent_basis(&normal);
+
+        let dx = (Vec3::dot(&radial, &tangent).abs() - self.half_width).max(0.);
+        let dz = (Vec3::dot(&radial, &bitangent).abs() - self.half_length).max(0.);
+        (height * height + dx * dx + dz * dz).sqrt()
+    }
+}
+
+// two unit directions inside the plane with the given unit normal, x and z for the normal (0, 1, 0). the
+// tangent comes from a reference axis that isn't parallel to the normal, the bitangent completes the basis
+fn tangent_basis(normal: &Vec3) -> (Vec3, Vec3) {
+    let reference = if normal.z.abs() < 0.9 { Vec3::new(0., 0., 1.) } else { Vec3::new(1., 0., 0.) };
+    let tangent = Vec3::cross(normal, &reference).normalize();
+    let bitangent = Vec3::cross(&tangent, normal);
+
+    (tangent, bitangent)
+}
+
+// splits an offset into the height above the plane with the given normal and the part inside the plane
+fn plane_coordinates(q: &Vec3, normal: &Vec3) -> (Real, Vec3) {
+    let normal = normal.normalize();
+    let height = Vec3::dot(q, &normal);
+
+    (height, q - normal * height)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Capsule {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub radius: Real,
+}
+
+impl DistanceFunction for Capsule {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let pa = p - self.a;
+        let ba = self.b - self.a;
+        let ba_sqr_length = ba.sqr_length();
+
+        // a degenerate capsule (a == b) is just a sphere around a
+        let h = if ba_sqr_length > 0. {
+            (Vec3::dot(&pa, &ba) / ba_sqr_length).clamp(0., 1.)
+        } else {
+            0.
+        };
+
+        (pa - ba * h).length() - self.radius
+    }
+}
+
+// cone https://iquilezles.org/articles/distfunctions
+// the apex sits at pos and the cone opens downwards along -y
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cone {
+    pub pos: Vec3,
+    pub angle: Real,
+    pub height: Real,
+}
+
+impl DistanceFunction for Cone {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let p2 = p - self.pos;
+
+        // the cone is rotationally symmetric, so we work in the 2d (radius, height) plane
+        let q = Vec2::new(self.height * self.angle.tan(), -self.height);
+        let w = Vec2::new((p2.x * p2.x + p2.z * p2.z).sqrt(), p2.y);
+
+        // closest point on the slanted side and on the base cap
+        let a = w - q * (Vec2::dot(&w, &q) / Vec2::dot(&q, &q)).clamp(0., 1.);
+        let b = w - Vec2::new(q.x * (w.x / q.x).clamp(0., 1.), q.y);
+
+        let d = Real::min(Vec2::dot(&a, &a), Vec2::dot(&b, &b));
+        let s = Real::max(-(w.x * q.y - w.y * q.x), -(w.y - q.y));
+
+        d.sqrt() * s.signum()
+    }
+}
+
+// approximate ellipsoid https://iquilezles.org/articles/ellipsoids
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ellipsoid {
+    pub pos: Vec3,
+    pub radii: Vec3,
+}
+
+impl DistanceFunction for Ellipsoid {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let p2 = p - self.pos;
+
+        let k0 = (p2 / self.radii).length();
+        let k1 = (p2 / (self.radii * self.radii)).length();
+
+        // the estimate is 0 / 0 at the center, which is as deep inside as the smallest radius
+        if k1 == 0. {
+            return -self.radii.min_element();
+        }
+
+        k0 * (k0 - 1.) / k1
+    }
+}
+
+// exact octahedron https://iquilezles.org/articles/distfunctions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Octahedron {
+    pub pos: Vec3,
+    pub size: Real,
+}
+
+impl DistanceFunction for Octahedron {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let p2 = (p - self.pos).abs();
+        let m = p2.x + p2.y + p2.z - self.size;
+
+        // find the face the point projects onto, if there is none it is closest to the face plane
+        let q = if 3. * p2.x < m {
+            p2
+        } else if 3. * p2.y < m {
+            Vec3::new(p2.y, p2.z, p2.x)
+        } else if 3. * p2.z < m {
+            Vec3::new(p2.z, p2.x, p2.y)
+        } else {
+            return m * 0.57735027;
+        };
+
+        let k = (0.5 * (q.z - q.y + self.size)).clamp(0., self.size);
+
+        Vec3::new(q.x, q.y - self.size + k, q.z - k).length()
+    }
+}
+
+// hexagonal prism https://iquilezles.org/articles/distfunctions
+// standing upright along y, radius is the distance to the flat sides and height is the half height
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HexPrism {
+    pub pos: Vec3,
+    pub radius: Real,
+    pub height: Real,
+}
+
+impl DistanceFunction for HexPrism {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let k = Vec3::new(-0.8660254, 0.5, 0.57735);
+        let p2 = (p - self.pos).abs();
+
+        // fold the point into a single sector of the hexagon, working in the 2d xz plane
+        let fold = 2. * Real::min(k.x * p2.x + k.y * p2.z, 0.);
+        let x = p2.x - fold * k.x;
+        let z = p2.z - fold * k.y;
+
+        let edge = x.clamp(-k.z * self.radius, k.z * self.radius);
+        let d_side = Vec2::new(x - edge, z - self.radius).length() * (z - self.radius).signum();
+        let d_cap = p2.y - self.height;
+
+        Real::min(Real::max(d_side, d_cap), 0.) + Vec2::max(&Vec2::new(d_side, d_cap), 0.).length()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundBox {
+    pub pos: Vec3,
+    pub size: Vec3,
+    pub radius: Real,
+}
+
+impl DistanceFunction for RoundBox {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        // shrink the box by the radius and inflate it again to round the edges
+        let q = (p - self.pos).abs() - self.size + self.radius;
+        Vec3::max(&q, 0.).length() + q.max_element().min(0.) - self.radius
+    }
+}
+
+// box frame https://iquilezles.org/articles/distfunctions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BoxFrame {
+    pub pos: Vec3,
+    pub size: Vec3,
+    pub edge: Real,
+}
+
+impl DistanceFunction for BoxFrame {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let p2 = (p - self.pos).abs() - self.size;
+        let q = (p2 + self.edge).abs() - self.edge;
+
+        // the frame is the union of the edges along each of the three axes
+        let box_distance = |b: Vec3| Vec3::max(&b, 0.).length() + b.max_element().min(0.);
+
+        Real::min(
+            Real::min(
+                box_distance(Vec3::new(p2.x, q.y, q.z)),
+                box_distance(Vec3::new(q.x, p2.y, q.z)),
+            ),
+            box_distance(Vec3::new(q.x, q.y, p2.z)),
+        )
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Union {
     pub a: DistanceField,
     pub b: DistanceField,
 }
 
 impl DistanceFunction for Union {
-    fn get_distance(&self, p: &Vec3) -> f64 {
-        f64::min(self.a.get_distance(p), self.b.get_distance(p))
+    fn get_distance(&self, p: &Vec3) -> Real {
+        Real::min(self.a.get_distance(p), self.b.get_distance(p))
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        if self.a.get_distance(p) < self.b.get_distance(p) {
+            self.a.get_material(p)
+        } else {
+            self.b.get_material(p)
+        }
+    }
+
+    // the gradient of the min is the gradient of the closer field
+    fn get_normal_analytic(&self, p: &Vec3) -> Option<Vec3> {
+        if self.a.get_distance(p) < self.b.get_distance(p) {
+            self.a.get_normal_analytic(p)
+        } else {
+            self.b.get_normal_analytic(p)
+        }
+    }
+}
+
+// polynomial smooth min https://iquilezles.org/articles/smin
+fn smooth_min(a: Real, b: Real, k: Real) -> Real {
+    // for a vanishing blend radius this is just the hard min
+    if k <= 0. {
+        return Real::min(a, b);
+    }
+
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0., 1.);
+    b * (1. - h) + a * h - k * h * (1. - h)
+}
+
+fn smooth_max(a: Real, b: Real, k: Real) -> Real {
+    -smooth_min(-a, -b, k)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmoothUnion {
+    pub a: DistanceField,
+    pub b: DistanceField,
+    pub k: Real,
+}
+
+impl DistanceFunction for SmoothUnion {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        smooth_min(self.a.get_distance(p), self.b.get_distance(p), self.k)
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        if self.a.get_distance(p) < self.b.get_distance(p) {
+            self.a.get_material(p)
+        } else {
+            self.b.get_material(p)
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Subtraction {
     pub a: DistanceField,
     pub b: DistanceField,
 }
 
 impl DistanceFunction for Subtraction {
-    fn get_distance(&self, p: &Vec3) -> f64 {
-        f64::max(-self.a.get_distance(p), self.b.get_distance(p))
+    fn get_distance(&self, p: &Vec3) -> Real {
+        Real::max(-self.a.get_distance(p), self.b.get_distance(p))
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        if -self.a.get_distance(p) > self.b.get_distance(p) {
+            self.a.get_material(p)
+        } else {
+            self.b.get_material(p)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmoothSubtraction {
+    pub a: DistanceField,
+    pub b: DistanceField,
+    pub k: Real,
+}
+
+impl DistanceFunction for SmoothSubtraction {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        smooth_max(-self.a.get_distance(p), self.b.get_distance(p), self.k)
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        if -self.a.get_distance(p) > self.b.get_distance(p) {
+            self.a.get_material(p)
+        } else {
+            self.b.get_material(p)
+        }
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Intersection {
     pub a: DistanceField,
     pub b: DistanceField,
 }
 
 impl DistanceFunction for Intersection {
-    fn get_distance(&self, p: &Vec3) -> f64 {
-        f64::max(self.a.get_distance(p), self.b.get_distance(p))
+    fn get_distance(&self, p: &Vec3) -> Real {
+        Real::max(self.a.get_distance(p), self.b.get_distance(p))
     }
-}
 
-// julia https://www.shadertoy.com/view/MsfGRr
-// https://www.shadertoy.com/view/3tsyzl
-/*
-float map( in vec3 p, out vec4 oTrap, in vec4 c )
-{
-    vec4 z = vec4(p,0.0);
-    float md2 = 1.0;
-    float mz2 = dot(z,z);
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        if self.a.get_distance(p) > self.b.get_distance(p) {
+            self.a.get_material(p)
+        } else {
+            self.b.get_material(p)
+        }
+    }
+}
 
-    vec4 trap = vec4(abs(z.xyz),dot(z,z));
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmoothIntersection {
+    pub a: DistanceField,
+    pub b: DistanceField,
+    pub k: Real,
+}
 
-    float n = 1.0;
-    for( int i=0; i<numIterations; i++ )
-    {
-        // dz -> 2·z·dz, meaning |dz| -> 2·|z|·|dz|
-        // Now we take the 2.0 out of the loop and do it at the end with an exp2
-        md2 *= 4.0*mz2;
-        // z  -> z^2 + c
-        z = qsqr(z) + c;
+impl DistanceFunction for SmoothIntersection {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        smooth_max(self.a.get_distance(p), self.b.get_distance(p), self.k)
+    }
 
-        trap = min( trap, vec4(abs(z.xyz),dot(z,z)) );
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        if self.a.get_distance(p) > self.b.get_distance(p) {
+            self.a.get_material(p)
+        } else {
+            self.b.get_material(p)
+        }
+    }
+}
 
-        mz2 = qlength2(z);
-        if(mz2>4.0) break;
-        n += 1.0;
+// a period of 0 disables the repetition along that axis
+// count limits the number of copies on each side of the origin
+fn repeat_axis(x: Real, period: Real, count: Real) -> Real {
+    if period == 0. {
+        return x;
     }
 
-    oTrap = trap;
+    x - period * (x / period).round().clamp(-count, count)
+}
 
-    return 0.25*sqrt(mz2/md2)*log(mz2);  // d = 0.5·|z|·log|z|/|z'|
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Repeat {
+    pub a: DistanceField,
+    pub period: Vec3,
 }
- */
 
-#[derive(Debug, Clone)]
-pub struct Julia {
-    pub pos: Vec3,
-    pub iterations: i32,
-    pub traps: bool,
-    pub c: Vec4,
-    pub cut: bool,
-    pub cut_y: f64,
+impl Repeat {
+    fn local_point(&self, p: &Vec3) -> Vec3 {
+        Vec3::new(
+            repeat_axis(p.x, self.period.x, Real::INFINITY),
+            repeat_axis(p.y, self.period.y, Real::INFINITY),
+            repeat_axis(p.z, self.period.z, Real::INFINITY),
+        )
+    }
 }
 
-impl DistanceFunction for Julia {
-    fn get_distance(&self, p: &Vec3) -> f64 {
-        let p2 = &(p - self.pos);
+impl DistanceFunction for Repeat {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.a.get_distance(&self.local_point(p))
+    }
 
-        // if p2.length() > 2. {
-        //     return p2.length() - 1.5;
-        // }
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(&self.local_point(p))
+    }
+}
 
-        let mut z = Vec4::from_vec3(p2, 0.);
-        let mut sqrt_derive_z = 1.;
-        let mut m2 = 0.;
-        let mut n = 0.;
-        let mut o = 1e10;
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepeatLimited {
+    pub a: DistanceField,
+    pub period: Vec3,
+    pub count: Vec3,
+}
 
-        for i in 0..self.iterations {
-            // z' = 3z² -> |z'|² = 9|z²|²
-            sqrt_derive_z *= 9. * z.q_square().sqr_length();
-            // z = z³ + c
-            z = z.q_cube() + self.c;
+impl RepeatLimited {
+    fn local_point(&self, p: &Vec3) -> Vec3 {
+        Vec3::new(
+            repeat_axis(p.x, self.period.x, self.count.x),
+            repeat_axis(p.y, self.period.y, self.count.y),
+            repeat_axis(p.z, self.period.z, self.count.z),
+        )
+    }
+}
 
-            m2 = z.sqr_length();
+impl DistanceFunction for RepeatLimited {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.a.get_distance(&self.local_point(p))
+    }
 
-            // orbit trapping : https://iquilezles.org/articles/orbittraps3d
-            if self.traps {
-                let new_o = ((z.x - 0.45).powi(2) + (z.z - 0.55).powi(2)).sqrt() - 0.1;
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(&self.local_point(p))
+    }
+}
 
-                o = f64::min(o, new_o);
-            }
+// like repeat_axis, but every other cell is mirrored, so neighbouring copies meet at their matching sides
+fn repeat_mirror_axis(x: Real, period: Real) -> Real {
+    if period == 0. {
+        return x;
+    }
 
-            if m2 > 256.0 {
-                break;
+    let cell = (x / period).round();
+    let local = x - period * cell;
+
+    if cell.rem_euclid(2.) == 0. {
+        local
+    } else {
+        -local
+    }
+}
+
+// infinite repetition that mirrors every other cell, which hides the seams between cells of asymmetric children
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepeatMirror {
+    pub a: DistanceField,
+    pub period: Vec3,
+}
+
+impl RepeatMirror {
+    fn local_point(&self, p: &Vec3) -> Vec3 {
+        Vec3::new(
+            repeat_mirror_axis(p.x, self.period.x),
+            repeat_mirror_axis(p.y, self.period.y),
+            repeat_mirror_axis(p.z, self.period.z),
+        )
+    }
+}
+
+impl DistanceFunction for RepeatMirror {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.a.get_distance(&self.local_point(p))
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(&self.local_point(p))
+    }
+}
+
+// rotation holds euler angles in radians, applied to the child around x, then y, then z
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Transform {
+    pub a: DistanceField,
+    pub rotation: Vec3,
+    pub translation: Vec3,
+}
+
+impl Transform {
+    // from the space of the parent into the local space of the child, undoes the translation first
+    // and then the rotations in reverse order
+    pub fn inverse_matrix(&self) -> Mat4 {
+        Mat4::rotation_x(-self.rotation.x)
+            * Mat4::rotation_y(-self.rotation.y)
+            * Mat4::rotation_z(-self.rotation.z)
+            * Mat4::translation(&-self.translation)
+    }
+
+    fn local_point(&self, p: &Vec3) -> Vec3 {
+        self.inverse_matrix().transform_point(p)
+    }
+}
+
+impl DistanceFunction for Transform {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.a.get_distance(&self.local_point(p))
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(&self.local_point(p))
+    }
+}
+
+// only uniform scaling is supported, scaling the axes differently stretches space unevenly,
+// which breaks the lipschitz bound of the child and the ray marcher would overstep the surface
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scale {
+    pub a: DistanceField,
+    pub factor: Real,
+}
+
+impl DistanceFunction for Scale {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        // a child scaled to nothing (or mirrored) is treated as empty space
+        if self.factor <= 0. {
+            return Real::INFINITY;
+        }
+
+        self.a.get_distance(&(p / self.factor)) * self.factor
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        if self.factor <= 0. {
+            return None;
+        }
+
+        self.a.get_material(&(p / self.factor))
+    }
+}
+
+// twisting is not distance preserving, for strong twists the result is only an approximate
+// distance (lipschitz > 1) and the ray marcher may overstep the surface at high amounts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Twist {
+    pub a: DistanceField,
+    pub amount: Real,
+}
+
+impl Twist {
+    fn local_point(&self, p: &Vec3) -> Vec3 {
+        // rotate the xz plane depending on the height
+        p.rotate_y(self.amount * p.y)
+    }
+}
+
+impl DistanceFunction for Twist {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.a.get_distance(&self.local_point(p))
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(&self.local_point(p))
+    }
+}
+
+// elongation https://iquilezles.org/articles/distfunctions
+// stretches the child around the origin by amount along each axis, the middle is filled with the cross section
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Elongate {
+    pub a: DistanceField,
+    pub amount: Vec3,
+}
+
+impl Elongate {
+    fn local_point(&self, p: &Vec3) -> Vec3 {
+        let h = self.amount.abs();
+
+        Vec3::new(
+            p.x - p.x.clamp(-h.x, h.x),
+            p.y - p.y.clamp(-h.y, h.y),
+            p.z - p.z.clamp(-h.z, h.z),
+        )
+    }
+}
+
+impl DistanceFunction for Elongate {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        // inside the stretched part the local point collapses onto the origin, so the interior distance
+        // is corrected by how far the point is from the border of the stretched box
+        let q = p.abs() - self.amount.abs();
+        self.a.get_distance(&self.local_point(p)) + q.max_element().min(0.)
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(&self.local_point(p))
+    }
+}
+
+// hollow shell around the surface of the child
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Onion {
+    pub a: DistanceField,
+    pub thickness: Real,
+}
+
+impl DistanceFunction for Onion {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.a.get_distance(p).abs() - self.thickness
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(p)
+    }
+}
+
+// inflates the child by radius, rounding off its edges
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Round {
+    pub a: DistanceField,
+    pub radius: Real,
+}
+
+impl DistanceFunction for Round {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.a.get_distance(p) - self.radius
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(p)
+    }
+
+    fn get_normal_analytic(&self, p: &Vec3) -> Option<Vec3> {
+        self.a.get_normal_analytic(p)
+    }
+}
+
+// perturbs the surface of the child with a sine pattern, for rocky or organic surfaces.
+// the displacement breaks the exact distance, the result can overestimate it by a factor of up to
+// 1 + amplitude * frequency * sqrt(3), so the rays may overstep thin parts. keeping amplitude * frequency
+// small keeps it close to exact, otherwise a lower accuracy and more iterations hide the artifacts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Displace {
+    pub a: DistanceField,
+    pub amplitude: Real,
+    pub frequency: Real,
+}
+
+impl DistanceFunction for Displace {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let q = p * self.frequency;
+        let displacement = self.amplitude * q.x.sin() * q.y.sin() * q.z.sin();
+
+        self.a.get_distance(p) + displacement
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(p)
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        let (min, max) = self.a.bounds()?;
+        Some((min - self.amplitude.abs(), max + self.amplitude.abs()))
+    }
+}
+
+// assigns a material to every surface of the child that doesn't have its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WithMaterial {
+    pub a: DistanceField,
+    pub material: Material,
+}
+
+impl DistanceFunction for WithMaterial {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.a.get_distance(p)
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(p).or(Some(self.material))
+    }
+
+    fn get_normal_analytic(&self, p: &Vec3) -> Option<Vec3> {
+        self.a.get_normal_analytic(p)
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.a.bounds()
+    }
+}
+
+// skips the evaluation of the child while the point is further than this away from its bounding box
+const BOUNDS_MARGIN: Real = 0.01;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bounded {
+    pub a: DistanceField,
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Bounded {
+    // derives the bounding box from the child, None if the child can't tell its bounds
+    pub fn new(a: DistanceField) -> Option<Bounded> {
+        let (min, max) = a.bounds()?;
+        Some(Bounded { a, min, max })
+    }
+
+    fn box_distance(&self, p: &Vec3) -> Real {
+        let center = (self.min + self.max) / 2.;
+        let half_size = (self.max - self.min) / 2.;
+        let q = (p - center).abs() - half_size;
+        Vec3::max(&q, 0.).length()
+    }
+}
+
+impl DistanceFunction for Bounded {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        // the box distance is a lower bound of the child distance, so it's a safe step size.
+        // close to the box the real distance is used, otherwise the rays would stop on the box surface
+        let box_distance = self.box_distance(p);
+        if box_distance > BOUNDS_MARGIN {
+            return box_distance;
+        }
+
+        self.a.get_distance(p)
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.a.get_material(p)
+    }
+
+    fn get_normal_analytic(&self, p: &Vec3) -> Option<Vec3> {
+        self.a.get_normal_analytic(p)
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some((self.min, self.max))
+    }
+}
+
+// bounding volume hierarchy over the leaves of a tree of unions, a sample only descends into the boxes
+// that are closer than the closest leaf found so far. build it with build_bvh
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bvh {
+    pub root: BvhNode,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BvhNode {
+    Leaf { field: DistanceField, min: Vec3, max: Vec3 },
+    Split { a: Box<BvhNode>, b: Box<BvhNode>, min: Vec3, max: Vec3 },
+}
+
+impl BvhNode {
+    fn bounds(&self) -> (Vec3, Vec3) {
+        match self {
+            BvhNode::Leaf { min, max, .. } => (*min, *max),
+            BvhNode::Split { min, max, .. } => (*min, *max),
+        }
+    }
+
+    // signed, so it is a lower bound of the distance to everything inside the box, inside the objects as well
+    fn box_distance(&self, p: &Vec3) -> Real {
+        let (min, max) = self.bounds();
+        let q = (p - (min + max) / 2.).abs() - (max - min) / 2.;
+        Vec3::max(&q, 0.).length() + Real::min(q.max_element(), 0.)
+    }
+
+    // the closest leaf below best, which holds the distance and leaf found so far
+    fn closest<'a>(&'a self, p: &Vec3, best: &mut (Real, Option<&'a DistanceField>)) {
+        if self.box_distance(p) >= best.0 {
+            return;
+        }
+
+        match self {
+            BvhNode::Leaf { field, .. } => {
+                let d = field.get_distance(p);
+                if d < best.0 {
+                    *best = (d, Some(field));
+                }
+            }
+            BvhNode::Split { a, b, .. } => {
+                // the closer child first, so the other one is more likely to be skipped
+                let (first, second) = if a.box_distance(p) <= b.box_distance(p) { (a, b) } else { (b, a) };
+
+                first.closest(p, best);
+                second.closest(p, best);
             }
+        }
+    }
+
+    fn build(mut leaves: Vec<(DistanceField, Vec3, Vec3)>) -> BvhNode {
+        if leaves.len() == 1 {
+            let (field, min, max) = leaves.pop().unwrap();
+            return BvhNode::Leaf { field, min, max };
+        }
+
+        let (mut min, mut max) = (leaves[0].1, leaves[0].2);
+        for (_, leaf_min, leaf_max) in &leaves[1..] {
+            min = Vec3::new(min.x.min(leaf_min.x), min.y.min(leaf_min.y), min.z.min(leaf_min.z));
+            max = Vec3::new(max.x.max(leaf_max.x), max.y.max(leaf_max.y), max.z.max(leaf_max.z));
+        }
+
+        // split at the median along the longest side of the box
+        let size = max - min;
+        let axis = if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        };
+
+        leaves.sort_by(|(_, min_a, max_a), (_, min_b, max_b)| (min_a[axis] + max_a[axis]).total_cmp(&(min_b[axis] + max_b[axis])));
+        let second_half = leaves.split_off(leaves.len() / 2);
+
+        BvhNode::Split {
+            a: Box::new(BvhNode::build(leaves)),
+            b: Box::new(BvhNode::build(second_half)),
+            min,
+            max,
+        }
+    }
+}
+
+fn collect_union_leaves(field: DistanceField, leaves: &mut Vec<DistanceField>) {
+    match field {
+        DistanceField::Union(union) => {
+            let Union { a, b } = *union;
+            collect_union_leaves(a, leaves);
+            collect_union_leaves(b, leaves);
+        }
+        _ => leaves.push(field),
+    }
+}
+
+// puts the leaves of a tree of unions that know their bounds into a bvh, the others stay in a union with it.
+// any other field is returned unchanged
+pub fn build_bvh(field: DistanceField) -> DistanceField {
+    let mut leaves = Vec::new();
+    collect_union_leaves(field, &mut leaves);
+
+    let mut bounded = Vec::new();
+    let mut unbounded = Vec::new();
 
-            n += 1.0;
+    for leaf in leaves {
+        match leaf.bounds() {
+            Some((min, max)) => bounded.push((leaf, min, max)),
+            None => unbounded.push(leaf),
         }
+    }
+
+    let mut fields = unbounded;
+
+    // a single leaf doesn't need a hierarchy
+    match bounded.len() {
+        0 => {}
+        1 => fields.push(bounded.pop().unwrap().0),
+        _ => fields.push(DistanceField::Bvh(Box::new(Bvh { root: BvhNode::build(bounded) }))),
+    }
+
+    fields.into_iter().reduce(|a, b| DistanceField::Union(Box::new(Union { a, b }))).unwrap()
+}
+
+impl DistanceFunction for Bvh {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let mut best = (Real::INFINITY, None);
+        self.root.closest(p, &mut best);
+        best.0
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        let mut best = (Real::INFINITY, None);
+        self.root.closest(p, &mut best);
+        best.1.and_then(|field| field.get_material(p))
+    }
+
+    fn get_normal_analytic(&self, p: &Vec3) -> Option<Vec3> {
+        let mut best = (Real::INFINITY, None);
+        self.root.closest(p, &mut best);
+        best.1.and_then(|field| field.get_normal_analytic(p))
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        Some(self.root.bounds())
+    }
+}
 
-        let mut d = 0.25 * m2.ln() * (m2 / sqrt_derive_z).sqrt();
+// a distance field implemented outside of this crate, it composes with the built in fields like any
+// other variant. it's shared between clones and can't be saved to or loaded from scene files
+#[derive(Clone)]
+pub struct Custom(pub Arc<dyn DistanceFunction + Send + Sync>);
+
+impl Custom {
+    pub fn new(field: impl DistanceFunction + Send + Sync + 'static) -> Custom {
+        Custom(Arc::new(field))
+    }
+}
+
+impl fmt::Debug for Custom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Custom")
+    }
+}
+
+impl DistanceFunction for Custom {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.0.get_distance(p)
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        self.0.get_material(p)
+    }
+
+    fn get_normal_analytic(&self, p: &Vec3) -> Option<Vec3> {
+        self.0.get_normal_analytic(p)
+    }
+
+    fn bounds(&self) -> Option<(Vec3, Vec3)> {
+        self.0.bounds()
+    }
+}
+
+// julia https://www.shadertoy.com/view/MsfGRr
+// https://www.shadertoy.com/view/3tsyzl
+/*
+float map( in vec3 p, out vec4 oTrap, in vec4 c )
+{
+    vec4 z = vec4(p,0.0);
+    float md2 = 1.0;
+    float mz2 = dot(z,z);
+
+    vec4 trap = vec4(abs(z.xyz),dot(z,z));
+
+    float n = 1.0;
+    for( int i=0; i<numIterations; i++ )
+    {
+        // dz -> 2·z·dz, meaning |dz| -> 2·|z|·|dz|
+        // Now we take the 2.0 out of the loop and do it at the end with an exp2
+        md2 *= 4.0*mz2;
+        // z  -> z^2 + c
+        z = qsqr(z) + c;
+
+        trap = min( trap, vec4(abs(z.xyz),dot(z,z)) );
+
+        mz2 = qlength2(z);
+        if(mz2>4.0) break;
+        n += 1.0;
+    }
+
+    oTrap = trap;
+
+    return 0.25*sqrt(mz2/md2)*log(mz2);  // d = 0.5·|z|·log|z|/|z'|
+}
+ */
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Julia {
+    pub pos: Vec3,
+    pub iterations: i32,
+    pub traps: bool,
+    pub coloring: bool,
+    pub c: Vec4,
+    pub cut: bool,
+    pub cut_y: Real,
+    // radius of a sphere around pos that contains the whole set, 0 disables the bound
+    pub bounding_radius: Real,
+}
+
+impl Julia {
+    // a copy with c interpolated between two constants, for morphing animations
+    pub fn with_lerped_c(&self, c_a: &Vec4, c_b: &Vec4, t: Real) -> Julia {
+        Julia {
+            c: Vec4::lerp(c_a, c_b, t),
+            ..self.clone()
+        }
+    }
+
+    // returns the distance and the smallest squared distance of the orbit to the origin
+    fn iterate(&self, p: &Vec3) -> (Real, Real) {
+        let p2 = &(p - self.pos);
+
+        // far away from the set the distance to the bounding sphere is a cheap lower bound
+        let bound_distance = p2.length() - self.bounding_radius;
+
+        if self.bounding_radius > 0. && bound_distance > 0. {
+            let mut d = bound_distance;
+
+            if self.cut {
+                d = Real::max(d, p.y);
+            }
+
+            return (d, Real::INFINITY);
+        }
+
+        // the derivative grows quickly over many iterations, so the orbit is always iterated in f64
+        let c = Quaternion::from(&self.c);
+        let mut z = Quaternion::new(widen(p2.x), widen(p2.y), widen(p2.z), 0.);
+        let mut sqrt_derive_z: f64 = 1.;
+        let mut m2: f64 = 0.;
+        let mut o: f64 = 1e10;
+        let mut color_trap: f64 = 1e10;
+
+        for _ in 0..self.iterations {
+            // z' = 3z² -> |z'|² = 9|z²|²
+            sqrt_derive_z *= 9. * z.square().sqr_length();
+            // z = z³ + c
+            z = z.cube() + c;
+
+            m2 = z.sqr_length();
+            color_trap = f64::min(color_trap, m2);
+
+            // orbit trapping : https://iquilezles.org/articles/orbittraps3d
+            if self.traps {
+                let new_o = ((z.r - 0.45).powi(2) + (z.j - 0.55).powi(2)).sqrt() - 0.1;
+
+                o = f64::min(o, new_o);
+            }
+
+            if m2 > 256.0 {
+                break;
+            }
+        }
+
+        let mut d = narrow(0.25 * m2.ln() * (m2 / sqrt_derive_z).sqrt());
+
+        // orbits that hit a critical point of the iteration have a zero or overflowing derivative,
+        // which happens more often for constants close to a change of the topology of the set.
+        // the estimate is meaningless (NaN or infinity) there, so stop the ray instead of overstepping
+        if !d.is_finite() {
+            d = 0.;
+        }
 
         if self.traps {
-            d = f64::min(d, o);
+            d = Real::min(d, narrow(o));
         }
 
         if self.cut {
-            d = f64::max(d, p.y);
+            d = Real::max(d, p.y);
+        }
+
+        (d, narrow(color_trap))
+    }
+}
+
+// cosine color palette https://iquilezles.org/articles/palettes
+fn palette(t: Real) -> Vec3 {
+    let phase = Vec3::new(0., 0.33, 0.67);
+    let angle = (phase + t) * consts::TAU;
+
+    Vec3::new(angle.x.cos(), angle.y.cos(), angle.z.cos()) * 0.5 + 0.5
+}
+
+impl DistanceFunction for Julia {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        self.iterate(p).0
+    }
+
+    fn get_material(&self, p: &Vec3) -> Option<Material> {
+        if !self.coloring {
+            return None;
+        }
+
+        // map the orbit trap to a color gradient
+        let (_, color_trap) = self.iterate(p);
+
+        Some(Material::new(palette(color_trap.sqrt())))
+    }
+}
+
+// mandelbulb http://blog.hvidtfeldts.net/index.php/2011/09/distance-estimated-3d-fractals-v-the-mandelbulb-different-de-approximations/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mandelbulb {
+    pub pos: Vec3,
+    pub power: Real,
+    pub iterations: i32,
+}
+
+impl DistanceFunction for Mandelbulb {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let p2 = p - self.pos;
+
+        let mut z = p2;
+        let mut dr = 1.;
+        let mut r = 0.;
+
+        for _ in 0..self.iterations {
+            r = z.length();
+
+            if r > 2. {
+                break;
+            }
+
+            // the spherical coordinates are undefined at the origin, which only happens for points of the set
+            if r == 0. {
+                return 0.;
+            }
+
+            // convert to spherical coordinates
+            let theta = (z.z / r).acos() * self.power;
+            let phi = z.y.atan2(z.x) * self.power;
+
+            dr = r.powf(self.power - 1.) * self.power * dr + 1.;
+
+            // z = z^power + c
+            let zr = r.powf(self.power);
+            z = Vec3::new(theta.sin() * phi.cos(), phi.sin() * theta.sin(), theta.cos()) * zr + p2;
+        }
+
+        0.5 * r.ln() * r / dr
+    }
+}
+
+// mandelbox http://blog.hvidtfeldts.net/index.php/2011/11/distance-estimated-3d-fractals-vi-the-mandelbox/
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Mandelbox {
+    pub pos: Vec3,
+    pub scale: Real,
+    pub iterations: i32,
+    pub fold_limit: Real,
+}
+
+impl DistanceFunction for Mandelbox {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let min_radius2 = 0.25;
+        let fixed_radius2 = 1.;
+
+        let offset = p - self.pos;
+
+        let mut z = offset;
+        let mut dr = 1.;
+
+        for _ in 0..self.iterations {
+            // box fold
+            z = z.clamp(-self.fold_limit, self.fold_limit) * 2. - z;
+
+            // sphere fold
+            let r2 = z.sqr_length();
+
+            if r2 < min_radius2 {
+                let t = fixed_radius2 / min_radius2;
+                z *= t;
+                dr *= t;
+            } else if r2 < fixed_radius2 {
+                let t = fixed_radius2 / r2;
+                z *= t;
+                dr *= t;
+            }
+
+            z = z * self.scale + offset;
+            dr = dr * self.scale.abs() + 1.;
+        }
+
+        z.length() / dr.abs()
+    }
+}
+
+// menger sponge https://iquilezles.org/articles/menger
+// a cube with the half size 1 around pos, every iteration carves three crosses at a third of the previous scale
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Menger {
+    pub pos: Vec3,
+    pub iterations: i32,
+}
+
+impl DistanceFunction for Menger {
+    fn get_distance(&self, p: &Vec3) -> Real {
+        let p = p - self.pos;
+
+        let q = p.abs() - 1.;
+        let mut d = Vec3::max(&q, 0.).length() + q.max_element().min(0.);
+
+        let mut scale = 1.;
+        for _ in 0..self.iterations {
+            // fold space into a single cell of the current scale, centered around the origin
+            let a = Vec3::new(
+                (p.x * scale).rem_euclid(2.) - 1.,
+                (p.y * scale).rem_euclid(2.) - 1.,
+                (p.z * scale).rem_euclid(2.) - 1.,
+            );
+            scale *= 3.;
+
+            // distance to the cross through the center of the cell
+            let r = (-(a.abs() * 3.) + 1.).abs();
+            let da = r.x.max(r.y);
+            let db = r.y.max(r.z);
+            let dc = r.z.max(r.x);
+            let c = (da.min(db).min(dc) - 1.) / scale;
+
+            d = d.max(c);
         }
 
         d
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+    use crate::ray_marching::create_ray_marcher;
+    use crate::rng::Rng;
+
+    #[test]
+    fn ellipsoid_with_equal_radii_is_a_sphere() {
+        let ellipsoid = Ellipsoid { pos: Vec3::new(1., 2., 3.), radii: Vec3::new(2., 2., 2.) };
+        let sphere = Sphere { pos: Vec3::new(1., 2., 3.), size: 2. };
+
+        for p in [Vec3::new(0., 0., 0.), Vec3::new(3., 2., 3.), Vec3::new(1., 2.5, 3.), Vec3::new(-4., 1., 7.)] {
+            assert!((ellipsoid.get_distance(&p) - sphere.get_distance(&p)).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn ellipsoid_center_is_inside() {
+        let ellipsoid = Ellipsoid { pos: Vec3::new(1., 2., 3.), radii: Vec3::new(1., 0.5, 2.) };
+
+        assert_eq!(ellipsoid.get_distance(&Vec3::new(1., 2., 3.)), -0.5);
+    }
+
+    #[test]
+    fn mandelbulb_is_finite_at_its_center() {
+        let mandelbulb = Mandelbulb { pos: Vec3::new(1., 2., 3.), power: 8., iterations: 10 };
+
+        assert_eq!(mandelbulb.get_distance(&Vec3::new(1., 2., 3.)), 0.);
+        assert!(mandelbulb.get_distance(&Vec3::new(4., 2., 3.)) > 0.);
+    }
+
+    #[test]
+    fn union_and_bvh_forward_the_analytic_normal_of_the_closer_field() {
+        let spheres = || {
+            DistanceField::Union(Box::new(Union {
+                a: DistanceField::Sphere(Sphere { pos: Vec3::new(-2., 0., 0.), size: 1. }),
+                b: DistanceField::Sphere(Sphere { pos: Vec3::new(2., 0., 0.), size: 1. }),
+            }))
+        };
+        let p = Vec3::new(3., 0., 0.);
+
+        let expected = Vec3::new(1., 0., 0.);
+        assert!(spheres().get_normal_analytic(&p).unwrap().approx_eq(&expected, 1e-6));
+        assert!(build_bvh(spheres()).get_normal_analytic(&p).unwrap().approx_eq(&expected, 1e-6));
+        assert!(spheres().get_normal_analytic(&Vec3::new(-3., 0., 0.)).unwrap().approx_eq(&-expected, 1e-6));
+    }
+
+    #[test]
+    fn bvh_matches_a_union_of_50_scattered_spheres() {
+        let mut rng = Rng::new(1);
+        let mut random_point = |size: Real| Vec3::new(rng.range(-size, size), rng.range(-size, size), rng.range(-size, size));
+
+        let spheres: Vec<DistanceField> = (0..50)
+            .map(|i| DistanceField::Sphere(Sphere { pos: random_point(10.), size: 0.2 + (i % 5) as Real * 0.2 }))
+            .collect();
+
+        let union = spheres
+            .into_iter()
+            .reduce(|a, b| DistanceField::Union(Box::new(Union { a, b })))
+            .unwrap();
+        let bvh = build_bvh(union.clone());
+        assert!(matches!(bvh, DistanceField::Bvh(_)));
+
+        for _ in 0..1000 {
+            let p = random_point(15.);
+            assert_eq!(bvh.get_distance(&p), union.get_distance(&p), "at {p:?}");
+        }
+    }
+
+    #[test]
+    fn cone_apex_and_base_edge_are_on_the_surface() {
+        let cone = Cone { pos: Vec3::new(1., 2., 3.), angle: 0.5, height: 2. };
+        let accuracy = create_ray_marcher(DistanceField::Cone(cone.clone())).accuracy;
+        let radius = 2. * Real::tan(0.5);
+
+        assert!(cone.get_distance(&Vec3::new(1., 2., 3.)).abs() < accuracy);
+        assert!(cone.get_distance(&Vec3::new(1. + radius, 0., 3.)).abs() < accuracy);
+        assert!(cone.get_distance(&Vec3::new(1., 0., 3. - radius)).abs() < accuracy);
+
+        // inside the middle of the cone and above the apex
+        assert!(cone.get_distance(&Vec3::new(1., 1., 3.)) < 0.);
+        assert!((cone.get_distance(&Vec3::new(1., 3., 3.)) - 1.).abs() < 1e-4);
+    }
+
+    fn two_spheres() -> (DistanceField, DistanceField) {
+        (
+            DistanceField::Sphere(Sphere { pos: Vec3::new(-0.6, 0., 0.), size: 1. }),
+            DistanceField::Sphere(Sphere { pos: Vec3::new(0.6, 0.3, 0.), size: 0.8 }),
+        )
+    }
+
+    fn sample_points() -> Vec<Vec3> {
+        let mut rng = Rng::new(4);
+        (0..200).map(|_| Vec3::new(rng.range(-3., 3.), rng.range(-3., 3.), rng.range(-3., 3.))).collect()
+    }
+
+    #[test]
+    fn smooth_union_converges_to_the_union() {
+        let (a, b) = two_spheres();
+        let union = Union { a: a.clone(), b: b.clone() };
+
+        for k in [0.1, 0.01, 0.001] {
+            let smooth = SmoothUnion { a: a.clone(), b: b.clone(), k };
+
+            for p in sample_points() {
+                let difference = union.get_distance(&p) - smooth.get_distance(&p);
+                // the blend only ever adds material, by at most k / 4
+                assert!((-1e-6..=k / 4. + 1e-6).contains(&difference), "k {k} at {p:?}");
+            }
+        }
+
+        let hard = SmoothUnion { a, b, k: 0. };
+        for p in sample_points() {
+            assert_eq!(hard.get_distance(&p), union.get_distance(&p));
+        }
+    }
+
+    #[test]
+    fn smooth_subtraction_and_intersection_with_k_0_are_hard() {
+        let (a, b) = two_spheres();
+        let subtraction = Subtraction { a: a.clone(), b: b.clone() };
+        let intersection = Intersection { a: a.clone(), b: b.clone() };
+        let smooth_subtraction = SmoothSubtraction { a: a.clone(), b: b.clone(), k: 0. };
+        let smooth_intersection = SmoothIntersection { a: a.clone(), b: b.clone(), k: 0. };
+
+        // nested next to the hard variants
+        let tree = DistanceField::Union(Box::new(Union {
+            a: DistanceField::SmoothSubtraction(Box::new(smooth_subtraction.clone())),
+            b: DistanceField::Intersection(Box::new(intersection.clone())),
+        }));
+
+        for p in sample_points() {
+            assert_eq!(smooth_subtraction.get_distance(&p), subtraction.get_distance(&p));
+            assert_eq!(smooth_intersection.get_distance(&p), intersection.get_distance(&p));
+            assert_eq!(tree.get_distance(&p), subtraction.get_distance(&p).min(intersection.get_distance(&p)));
+        }
+    }
+
+    #[test]
+    fn smooth_subtraction_and_intersection_stay_close_for_small_k() {
+        let (a, b) = two_spheres();
+        let subtraction = Subtraction { a: a.clone(), b: b.clone() };
+        let intersection = Intersection { a: a.clone(), b: b.clone() };
+        let smooth_subtraction = SmoothSubtraction { a: a.clone(), b: b.clone(), k: 0.01 };
+        let smooth_intersection = SmoothIntersection { a, b, k: 0.01 };
+
+        for p in sample_points() {
+            // the smooth max only ever removes material, by at most k / 4
+            let difference = smooth_subtraction.get_distance(&p) - subtraction.get_distance(&p);
+            assert!((-1e-6..=0.0025 + 1e-6).contains(&difference), "subtraction at {p:?}");
+
+            let difference = smooth_intersection.get_distance(&p) - intersection.get_distance(&p);
+            assert!((-1e-6..=0.0025 + 1e-6).contains(&difference), "intersection at {p:?}");
+        }
+    }
+
+    #[test]
+    fn repeat_limited_ends_after_the_last_copy() {
+        // a row of 3 spheres at x = -2, 0 and 2
+        let row = RepeatLimited {
+            a: DistanceField::Sphere(Sphere { pos: Vec3::zero(), size: 0.5 }),
+            period: Vec3::new(2., 0., 0.),
+            count: Vec3::new(1., 0., 0.),
+        };
+
+        assert!((row.get_distance(&Vec3::new(2.6, 0., 0.)) - 0.1).abs() < 1e-6);
+        assert!((row.get_distance(&Vec3::new(-2.6, 0., 0.)) - 0.1).abs() < 1e-6);
+        assert!((row.get_distance(&Vec3::new(2., 0.7, 0.)) - 0.2).abs() < 1e-6);
+        // an unlimited repetition would have another sphere at x = 4
+        assert!((row.get_distance(&Vec3::new(4., 0., 0.)) - 1.5).abs() < 1e-6);
+        assert!((row.get_distance(&Vec3::new(-4., 0., 0.)) - 1.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn transform_rotates_a_cuboid_about_y() {
+        let cube = Transform {
+            a: DistanceField::Cuboid(Cuboid { pos: Vec3::zero(), size: Vec3::new(1., 1., 1.) }),
+            rotation: Vec3::new(0., consts::FRAC_PI_4, 0.),
+            translation: Vec3::new(3., 0., 0.),
+        };
+        let diagonal = consts::SQRT_2;
+
+        // the corners of the rotated cube point along x and z
+        assert!(cube.get_distance(&Vec3::new(3. + diagonal, 1., 0.)).abs() < 1e-6);
+        assert!(cube.get_distance(&Vec3::new(3., 1., diagonal)).abs() < 1e-6);
+        assert!(cube.get_distance(&Vec3::new(3. - diagonal, -1., 0.)).abs() < 1e-6);
+        // where the corner of the axis aligned cube was, there is empty space now
+        assert!(cube.get_distance(&Vec3::new(4., 1., 1.)) > 0.2);
+        assert!((cube.get_distance(&Vec3::new(3. + diagonal + 0.5, 0., 0.)) - 0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn octahedron_vertices_are_on_the_surface() {
+        let octahedron = Octahedron { pos: Vec3::new(1., 2., 3.), size: 1.5 };
+
+        for axis in [Vec3::new(1., 0., 0.), Vec3::new(0., 1., 0.), Vec3::new(0., 0., 1.)] {
+            assert!(octahedron.get_distance(&(octahedron.pos + axis * 1.5)).abs() < 1e-6);
+            assert!(octahedron.get_distance(&(octahedron.pos - axis * 1.5)).abs() < 1e-6);
+        }
+
+        assert!((octahedron.get_distance(&octahedron.pos) + 1.5 / Real::sqrt(3.)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn hex_prism_flat_faces_and_caps() {
+        let prism = HexPrism { pos: Vec3::new(1., 2., 3.), radius: 1., height: 0.5 };
+
+        // the flat faces are at distance radius along z, the corners are further out along x
+        assert!(prism.get_distance(&Vec3::new(1., 2., 3.9)) < 0.);
+        assert!(prism.get_distance(&Vec3::new(1., 2., 4.)).abs() < 1e-4);
+        assert!((prism.get_distance(&Vec3::new(1., 2., 4.2)) - 0.2).abs() < 1e-4);
+        assert!((prism.get_distance(&Vec3::new(1., 2., 3.2)) + 0.5).abs() < 1e-4);
+
+        // the caps are height above and below the center
+        assert!(prism.get_distance(&Vec3::new(1., 2.4, 3.)) < 0.);
+        assert!(prism.get_distance(&Vec3::new(1., 2.5, 3.)).abs() < 1e-4);
+        assert!((prism.get_distance(&Vec3::new(1., 2.8, 3.)) - 0.3).abs() < 1e-4);
+        assert!((prism.get_distance(&Vec3::new(1., 1.2, 3.)) - 0.3).abs() < 1e-4);
+    }
+
+    #[test]
+    fn round_box_with_radius_0_is_a_cuboid() {
+        let round_box = RoundBox { pos: Vec3::new(1., 2., 3.), size: Vec3::new(1., 0.5, 2.), radius: 0. };
+        let cuboid = Cuboid { pos: Vec3::new(1., 2., 3.), size: Vec3::new(1., 0.5, 2.) };
+
+        for p in sample_points() {
+            assert_eq!(round_box.get_distance(&p), cuboid.get_distance(&p));
+        }
+    }
+
+    #[test]
+    fn round_box_rounds_the_corners() {
+        let round_box = RoundBox { pos: Vec3::zero(), size: Vec3::new(1., 1., 1.), radius: 0.25 };
+
+        // the faces stay in place, the corner is cut off by a sphere of the radius
+        assert!(round_box.get_distance(&Vec3::new(1., 0., 0.)).abs() < 1e-6);
+        let corner = Vec3::new(0.75, 0.75, 0.75) + Vec3::new(1., 1., 1.).normalize() * 0.25;
+        assert!(round_box.get_distance(&corner).abs() < 1e-6);
+        assert!(round_box.get_distance(&Vec3::new(1., 1., 1.)) > 0.);
+    }
+
+    #[test]
+    fn box_frame_is_hollow() {
+        let frame = BoxFrame { pos: Vec3::new(1., 2., 3.), size: Vec3::new(1., 1., 1.), edge: 0.1 };
+
+        assert!(frame.get_distance(&Vec3::new(2., 3., 4.)).abs() < 1e-6);
+        assert!(frame.get_distance(&Vec3::new(1.95, 2.95, 3.95)) < 0.);
+        // the edges are 2 * edge thick, the middle of an edge is part of the frame, the middle of a face is not
+        assert!(frame.get_distance(&Vec3::new(1., 3., 4.)).abs() < 1e-6);
+        assert!(frame.get_distance(&Vec3::new(1., 2.8, 3.8)).abs() < 1e-6);
+        assert!((frame.get_distance(&Vec3::new(2., 2., 3.)) - 0.8).abs() < 1e-4);
+        // the center is far away from all edges
+        assert!((frame.get_distance(&Vec3::new(1., 2., 3.)) - 0.8 * consts::SQRT_2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn torus_hole_is_centered_on_pos() {
+        let torus = Torus { pos: Vec3::new(5., 0., 0.), axis: Vec3::new(0., 1., 0.), outer_size: 1., inner_size: 0.25 };
+
+        // the hole is at pos, the tube runs around it at outer_size
+        assert!((torus.get_distance(&Vec3::new(5., 0., 0.)) - 0.75).abs() < 1e-6);
+        assert!((torus.get_distance(&Vec3::new(6., 0., 0.)) + 0.25).abs() < 1e-6);
+        assert!((torus.get_distance(&Vec3::new(5., 0., -1.)) + 0.25).abs() < 1e-6);
+        // a torus that ignores pos would have its tube around the origin
+        assert!(torus.get_distance(&Vec3::new(1., 0., 0.)) > 2.);
+    }
+
+    #[test]
+    fn torus_stands_upright_along_its_axis() {
+        let torus = Torus { pos: Vec3::new(5., 0., 0.), axis: Vec3::new(0., 0., 2.), outer_size: 1., inner_size: 0.25 };
+
+        // the ring lies in the xy plane now
+        assert!((torus.get_distance(&Vec3::new(5., 1., 0.)) + 0.25).abs() < 1e-6);
+        assert!((torus.get_distance(&Vec3::new(4., 0., 0.)) + 0.25).abs() < 1e-6);
+        assert!((torus.get_distance(&Vec3::new(5., 0., 1.)) - (consts::SQRT_2 - 0.25)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn plane_distance_is_positive_on_the_side_of_the_normal() {
+        // a floor at y = -1
+        let floor = Plane { normal: Vec3::new(0., 1., 0.), h: 1. };
+
+        assert!(floor.get_distance(&Vec3::new(3., -1., 7.)).abs() < 1e-6);
+        assert!((floor.get_distance(&Vec3::new(0., 0., 0.)) - 1.).abs() < 1e-6);
+        assert!((floor.get_distance(&Vec3::new(-4., 2., 1.)) - 3.).abs() < 1e-6);
+        assert!((floor.get_distance(&Vec3::new(0., -3., 0.)) + 2.).abs() < 1e-6);
+
+        // the normal is normalized before use
+        let scaled = Plane { normal: Vec3::new(0., 4., 0.), h: 1. };
+        assert!((scaled.get_distance(&Vec3::new(-4., 2., 1.)) - 3.).abs() < 1e-6);
+    }
+
+    #[test]
+    fn menger_without_iterations_is_a_cube() {
+        let menger = Menger { pos: Vec3::new(1., 2., 3.), iterations: 0 };
+        let cube = Cuboid { pos: Vec3::new(1., 2., 3.), size: Vec3::new(1., 1., 1.) };
+
+        for p in sample_points() {
+            assert_eq!(menger.get_distance(&p), cube.get_distance(&p));
+        }
+    }
+
+    #[test]
+    fn menger_holes_are_outside() {
+        let menger = Menger { pos: Vec3::new(1., 2., 3.), iterations: 3 };
+
+        // the first iteration carves a cross through the middle third of each face
+        assert!((menger.get_distance(&Vec3::new(1., 2., 3.)) - 1. / 3.).abs() < 1e-6);
+        assert!(menger.get_distance(&Vec3::new(1.9, 2., 3.)) > 0.);
+        assert!(menger.get_distance(&Vec3::new(1., 2.1, 3.9)) > 0.);
+        // the corners stay solid, up to the holes of the later iterations
+        assert!(menger.get_distance(&Vec3::new(1.95, 2.95, 3.95)) < 0.);
+    }
+
+    #[test]
+    fn capped_torus_with_a_full_cap_is_a_torus() {
+        // the cap is the half angle of the arc, pi closes the ring
+        let (sin, cos) = consts::PI.sin_cos();
+        let capped = CappedTorus { pos: Vec3::new(1., 2., 3.), outer_size: 1., inner_size: 0.25, cap: (sin, cos) };
+        let torus = Torus { pos: Vec3::new(1., 2., 3.), axis: Vec3::new(0., 0., 1.), outer_size: 1., inner_size: 0.25 };
+
+        for p in sample_points() {
+            assert!((capped.get_distance(&p) - torus.get_distance(&p)).abs() < 1e-5, "at {p:?}");
+        }
+    }
+
+    #[test]
+    fn capped_torus_ends_at_the_cap() {
+        // a half ring over the top, the ends of the arc are at (+-1, 0)
+        let (sin, cos) = consts::FRAC_PI_2.sin_cos();
+        let capped = CappedTorus { pos: Vec3::zero(), outer_size: 1., inner_size: 0.25, cap: (sin, cos) };
+
+        assert!((capped.get_distance(&Vec3::new(0., 1., 0.)) + 0.25).abs() < 1e-6);
+        // the bottom half of the ring is missing, it's as far as the ends of the arc
+        assert!((capped.get_distance(&Vec3::new(0., -1., 0.)) - (consts::SQRT_2 - 0.25)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn repeat_mirror_mirrors_neighbouring_cells() {
+        // a sphere off the center of the cell, so the mirroring shows
+        let mirrored = RepeatMirror {
+            a: DistanceField::Sphere(Sphere { pos: Vec3::new(0.3, 0., 0.), size: 0.2 }),
+            period: Vec3::new(2., 0., 0.),
+        };
+
+        let mut rng = Rng::new(81);
+
+        for _ in 0..100 {
+            let p = Vec3::new(rng.range(-1., 1.), rng.range(-1., 1.), rng.range(-1., 1.));
+
+            // the cells meet at x = 1, the next cell is the mirror image of the first one
+            let d = mirrored.get_distance(&p);
+            assert!((mirrored.get_distance(&Vec3::new(2. - p.x, p.y, p.z)) - d).abs() < 1e-6);
+            // and every other cell is a plain copy
+            assert!((mirrored.get_distance(&Vec3::new(p.x + 4., p.y, p.z)) - d).abs() < 1e-5);
+        }
+
+        // the spheres of the first two cells are at 0.3 and 1.7
+        assert!((mirrored.get_distance(&Vec3::new(1.7, 0., 0.)) + 0.2).abs() < 1e-6);
+        assert!((mirrored.get_distance(&Vec3::new(2.3, 0., 0.)) - 0.4).abs() < 1e-6);
+    }
+
+    #[test]
+    fn elongated_sphere_is_a_capsule() {
+        let elongated = Elongate {
+            a: DistanceField::Sphere(Sphere { pos: Vec3::zero(), size: 0.5 }),
+            amount: Vec3::new(1., 0., 0.),
+        };
+
+        // round caps 1 + 0.5 from the center, a straight tube of radius 0.5 in between
+        assert!(elongated.get_distance(&Vec3::new(1.5, 0., 0.)).abs() < 1e-6);
+        assert!(elongated.get_distance(&Vec3::new(-1.5, 0., 0.)).abs() < 1e-6);
+        assert!(elongated.get_distance(&Vec3::new(0.7, 0.5, 0.)).abs() < 1e-6);
+        assert!(elongated.get_distance(&Vec3::new(-0.3, 0., -0.5)).abs() < 1e-6);
+        assert!((elongated.get_distance(&Vec3::new(2.5, 0., 0.)) - 1.).abs() < 1e-6);
+        assert!((elongated.get_distance(&Vec3::new(1., 0., 0.)) + 0.5).abs() < 1e-6);
+        assert!((elongated.get_distance(&Vec3::new(0., 0., 0.)) + 0.5).abs() < 1e-6);
+    }
+
+    // the Vec4 quaternion square from before the Quaternion type
+    fn q_square(v: &Vec4) -> Vec4 {
+        Vec4 {
+            x: v.x * v.x - v.y * v.y - v.z * v.z - v.w * v.w,
+            y: v.x * v.y * 2.,
+            z: v.x * v.z * 2.,
+            w: v.x * v.w * 2.,
+        }
+    }
+
+    // the Vec4 quaternion cube from before the Quaternion type, it relies on the fixed w component of `v * v`
+    fn q_cube(v: &Vec4) -> Vec4 {
+        let sqr_v = v * v;
+        let new_x_factor = sqr_v.x - sqr_v.y * 3. - sqr_v.z * 3. - sqr_v.w * 3.;
+        let new_other_factor = sqr_v.x * 3. - sqr_v.y - sqr_v.z - sqr_v.w;
+
+        Vec4 {
+            x: v.x * new_x_factor,
+            y: v.y * new_other_factor,
+            z: v.z * new_other_factor,
+            w: v.w * new_other_factor,
+        }
+    }
+
+    // the julia iteration as it was written against Vec4 before the Quaternion type
+    fn julia_reference(julia: &Julia, p: &Vec3) -> Real {
+        let mut z = Vec4::from_vec3(&(p - julia.pos), 0.);
+        let mut sqrt_derive_z: Real = 1.;
+        let mut m2: Real = 0.;
+
+        for _ in 0..julia.iterations {
+            sqrt_derive_z *= 9. * q_square(&z).sqr_length();
+            z = q_cube(&z) + julia.c;
+
+            m2 = z.sqr_length();
+            if m2 > 256. {
+                break;
+            }
+        }
+
+        0.25 * m2.ln() * (m2 / sqrt_derive_z).sqrt()
+    }
+
+    #[test]
+    fn julia_has_the_silhouette_of_the_reference_iteration() {
+        let julia = Julia {
+            pos: Vec3::new(0., 0., -1.),
+            iterations: 12,
+            traps: false,
+            coloring: false,
+            c: Vec4::new(-1.51, 5.9, 4., -2.) / 10.,
+            cut: false,
+            cut_y: 0.,
+            bounding_radius: 0.,
+        };
+
+        let mut rng = Rng::new(84);
+        let mut inside = 0;
+
+        for _ in 0..500 {
+            let p = julia.pos + Vec3::new(rng.range(-1.5, 1.5), rng.range(-1.5, 1.5), rng.range(-1.5, 1.5));
+            let d = julia.get_distance(&p);
+            let reference = julia_reference(&julia, &p);
+
+            if reference.is_finite() {
+                assert!((d - reference).abs() <= 1e-4 * reference.abs().max(1.), "{d} vs {reference} at {p:?}");
+            }
+
+            if d <= 0. {
+                inside += 1;
+            }
+        }
+
+        // the samples have to hit the set for the comparison to mean something
+        assert!(inside > 0);
+    }
+
+    // a field from outside of the crate that keeps its own state
+    struct Counted {
+        sphere: Sphere,
+        calls: AtomicUsize,
+    }
+
+    impl DistanceFunction for Counted {
+        fn get_distance(&self, p: &Vec3) -> Real {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.sphere.get_distance(p)
+        }
+    }
+
+    #[test]
+    fn scene_with_a_custom_field_is_shared_between_threads() {
+        let counted = Arc::new(Counted { sphere: Sphere { pos: Vec3::zero(), size: 1. }, calls: AtomicUsize::new(0) });
+        let scene = DistanceField::Union(Box::new(Union {
+            a: DistanceField::Custom(Custom(counted.clone())),
+            b: DistanceField::Sphere(Sphere { pos: Vec3::new(5., 0., 0.), size: 1. }),
+        }));
+
+        let distances: Vec<Real> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..4)
+                .map(|i| {
+                    let scene = &scene;
+                    scope.spawn(move || scene.get_distance(&Vec3::new(0., i as Real + 2., 0.)))
+                })
+                .collect();
+
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+        });
+
+        assert_eq!(distances, vec![1., 2., 3., 4.]);
+        assert_eq!(counted.calls.load(Ordering::Relaxed), 4);
+    }
+
+    #[test]
+    fn plane_offset_is_along_the_unit_normal() {
+        // h is measured along the normalized normal, so a longer normal doesn't move the plane
+        let floor = Plane { normal: Vec3::new(0., 2., 0.), h: 1. };
+
+        assert!(floor.get_distance(&Vec3::new(0., -1., 0.)).abs() < 1e-6);
+        assert!(floor.get_distance(&Vec3::new(5., -1., -2.)).abs() < 1e-6);
+        assert!(floor.get_distance(&Vec3::new(0., -0.5, 0.)).abs() > 0.4);
+    }
+
+    fn checker_color(plane: &CheckerPlane, p: &Vec3) -> Vec3 {
+        plane.get_material(p).unwrap().color
+    }
+
+    #[test]
+    fn checker_floor_alternates_along_x_and_z() {
+        let floor = CheckerPlane { normal: Vec3::new(0., 1., 0.), h: 0., scale: 1., color_a: Vec3::new(1., 1., 1.), color_b: Vec3::zero() };
+
+        for (x, z) in [(0.5, 0.5), (1.5, 0.5), (0.5, 1.5), (-0.5, 0.5), (2.5, -3.5)] {
+            let parity = (x as Real).floor() + (z as Real).floor();
+            let expected = if parity.rem_euclid(2.) == 0. { floor.color_a } else { floor.color_b };
+            assert!(checker_color(&floor, &Vec3::new(x, 0., z)).approx_eq(&expected, 1e-6), "at {x}, {z}");
+        }
+    }
+
+    #[test]
+    fn checker_wall_alternates_inside_the_plane() {
+        let white = Vec3::new(1., 1., 1.);
+        let wall = CheckerPlane { normal: Vec3::new(1., 0., 0.), h: 2., scale: 1., color_a: white, color_b: Vec3::zero() };
+
+        // the wall is at x = -2, stepping one tile up or sideways flips the color
+        let base = checker_color(&wall, &Vec3::new(-2., 0.5, 0.5));
+        assert!(!checker_color(&wall, &Vec3::new(-2., 1.5, 0.5)).approx_eq(&base, 1e-6));
+        assert!(!checker_color(&wall, &Vec3::new(-2., 0.5, 1.5)).approx_eq(&base, 1e-6));
+        assert!(checker_color(&wall, &Vec3::new(-2., 1.5, 1.5)).approx_eq(&base, 1e-6));
+
+        // a tilted plane gets checks along its own directions too
+        let tilted = CheckerPlane { normal: Vec3::new(0., 1., 1.), ..wall };
+        let (tangent, bitangent) = tangent_basis(&tilted.normal.normalize());
+        let center = (tangent + bitangent) * 0.5;
+        let base = checker_color(&tilted, &center);
+        assert!(!checker_color(&tilted, &(center + tangent)).approx_eq(&base, 1e-6));
+        assert!(!checker_color(&tilted, &(center + bitangent)).approx_eq(&base, 1e-6));
+        assert!(checker_color(&tilted, &(center + tangent + bitangent)).approx_eq(&base, 1e-6));
+    }
 }
\ No newline at end of file
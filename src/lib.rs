@@ -0,0 +1,34 @@
+#[path = "math/quaternion.rs"]
+pub mod quaternion;
+#[path = "math/real.rs"]
+pub mod real;
+#[path = "math/vec2.rs"]
+pub mod vec2;
+#[path = "math/vec3.rs"]
+pub mod vec3;
+#[path = "math/vec4.rs"]
+pub mod vec4;
+#[path = "math/mat4.rs"]
+pub mod mat4;
+#[path = "math/ray.rs"]
+pub mod ray;
+#[path = "ray_marching.rs"]
+pub mod ray_marching;
+#[path = "distance_fields.rs"]
+pub mod distance_fields;
+#[path = "camera.rs"]
+pub mod camera;
+#[path = "material.rs"]
+pub mod material;
+#[path = "scene.rs"]
+pub mod scene;
+#[path = "light.rs"]
+pub mod light;
+#[path = "rng.rs"]
+pub mod rng;
+#[path = "noise.rs"]
+pub mod noise;
+#[path = "render.rs"]
+pub mod render;
+#[path = "animation.rs"]
+pub mod animation;
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::real::Real;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Light {
+    Directional {
+        dir: Vec3,
+        color: Vec3,
+        intensity: Real,
+    },
+    Point {
+        pos: Vec3,
+        color: Vec3,
+        intensity: Real,
+    },
+}
+
+impl Light {
+    // direction towards the light, distance to the light and incoming light at p. point lights closer than
+    // min_dist are treated as min_dist away, so a light on the surface doesn't divide by 0
+    pub fn illuminate(&self, p: &Vec3, min_dist: Real) -> (Vec3, Real, Vec3) {
+        match self {
+            Light::Directional { dir, color, intensity } => {
+                (-dir, Real::INFINITY, color * *intensity)
+            }
+            Light::Point { pos, color, intensity } => {
+                let to_light = pos - p;
+                let dist = to_light.length();
+                let clamped = dist.max(min_dist);
+
+                // inverse square falloff
+                (to_light / clamped, dist, color * (intensity / (clamped * clamped)))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_light_falls_off_with_the_square_of_the_distance() {
+        let light = Light::Point { pos: Vec3::new(0., 2., 0.), color: Vec3::new(1., 1., 1.), intensity: 8. };
+        let (dir, dist, incoming) = light.illuminate(&Vec3::zero(), 1e-3);
+
+        assert!(dir.approx_eq(&Vec3::new(0., 1., 0.), 1e-6));
+        assert!((dist - 2.).abs() < 1e-6);
+        assert!(incoming.approx_eq(&Vec3::new(2., 2., 2.), 1e-5));
+    }
+
+    #[test]
+    fn point_light_on_the_point_stays_finite() {
+        let light = Light::Point { pos: Vec3::new(1., 2., 3.), color: Vec3::new(1., 1., 1.), intensity: 1. };
+
+        for p in [Vec3::new(1., 2., 3.), Vec3::new(1., 2., 3.0001)] {
+            let (dir, _, incoming) = light.illuminate(&p, 1e-3);
+
+            assert!(dir.length() <= 1. + 1e-6 && dir.x.is_finite() && dir.y.is_finite() && dir.z.is_finite());
+            assert!(incoming.x.is_finite() && incoming.x <= 1e6);
+        }
+    }
+}
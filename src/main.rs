@@ -1,143 +1,204 @@
-use std::{mem, thread};
+use std::{env, io, mem, process};
 use std::io::Write;
-use std::ops::Deref;
-use std::sync::Arc;
-use std::thread::JoinHandle;
 use std::time::Instant;
 
-use image::{Rgb, RgbImage};
-use itertools::{iproduct, Itertools};
-use rayon::prelude::*;
-
-use distance_fields::DistanceField;
-use distance_fields::DistanceFunction;
-use ray::Ray;
-use ray_marching::create_ray_marcher;
-use ray_marching::RayMarcher;
-use vec3::Vec3;
-use vec4::Vec4;
-
-#[path = "math/vec3.rs"]
-mod vec3;
-#[path = "math/vec4.rs"]
-mod vec4;
-#[path = "math/ray.rs"]
-mod ray;
-#[path = "ray_marching.rs"]
-mod ray_marching;
-#[path = "distance_fields.rs"]
-mod distance_fields;
-
-const ASPECT_RATIO: f64 = 16. / 9.;
-const IMAGE_WIDTH: u32 = 1920;
-const IMAGE_HEIGHT: u32 = (IMAGE_WIDTH as f32 / ASPECT_RATIO as f32) as u32;
+use image::DynamicImage;
 
-fn main() {
-    let sphere: DistanceField = DistanceField::Sphere(distance_fields::Sphere {
-        pos: Vec3::new(0., 0., -2.),
-        size: 0.2,
-    });
-    // // let cuboid: DistanceField = DistanceField::Cuboid(distance_fields::Cuboid {
-    // //     pos: Vec3::new(0.8, 0., -2.),
-    // //     size: Vec3::new(0.5, 0.7, 0.5),
-    // // });
-    //
-    // let sphere2: DistanceField = DistanceField::Sphere(distance_fields::Sphere {
-    //     pos: Vec3::new(0.8, 0., -2.),
-    //     size: 0.6,
-    // });
-    // let sdf: DistanceField = DistanceField::Union(Box::from(distance_fields::Union {
-    //     a: sphere,
-    //     b: sphere2,
-    // }));
+use sphere_tracer::animation::{orbit_camera, render_animation};
+use sphere_tracer::camera::create_camera;
+use sphere_tracer::camera::Camera;
+use sphere_tracer::distance_fields::{self, build_bvh, DistanceField};
+use sphere_tracer::ray_marching::{create_ray_marcher, Ambient};
+use sphere_tracer::ray_marching::RayMarcher;
+use sphere_tracer::ray_marching::RayMarcherBuilder;
+use sphere_tracer::real::Real;
+use sphere_tracer::render::{render_pass, RenderPass, RenderSettings, RenderStats};
+use sphere_tracer::scene::{load_scene, SceneFile};
+use sphere_tracer::vec3::Vec3;
+use sphere_tracer::vec4::Vec4;
 
-    let julia = DistanceField::Julia(distance_fields::Julia {
-        pos: Vec3::new(0., 0., -1.),
-        iterations: 2000,
-        // -5, 2, 4, -2
+use cli::parse_args;
 
-        c: Vec4::new(-1.51, 5.9, 4., -2.) / 10.,
-        traps: false,
-        cut: true,
-        cut_y: 0.,
-    });
+#[path = "cli.rs"]
+mod cli;
 
-    let ray_marcher = create_ray_marcher(julia);
+fn main() {
+    let args = parse_args(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
 
-    let image = create_image(ray_marcher);
+    let (mut ray_marcher, camera, mut settings) = match &args.scene {
+        // big unions of bounded objects are only evaluated where they are close
+        Some(path) => match SceneFile::load(path) {
+            Ok(scene_file) => {
+                let ray_marcher = RayMarcherBuilder::new(build_bvh(scene_file.scene))
+                    .lights(scene_file.lights)
+                    .build();
+
+                (ray_marcher, scene_file.camera, scene_file.settings)
+            }
+            // files with only a distance field are rendered with the camera, lights and settings of the default scene
+            Err(err) => {
+                let scene = load_scene(path).unwrap_or_else(|_| {
+                    eprintln!("{err}");
+                    process::exit(1);
+                });
+                let (_, camera, settings) = default_setup();
+
+                (create_ray_marcher(build_bvh(scene)), camera, settings)
+            }
+        },
+        None => default_setup(),
+    };
+
+    // explicitly passed arguments win over the scene file
+    args.apply(&mut settings);
+    settings.validate().and_then(|_| settings.validate_for(&camera)).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
 
-    image.save("out.png").unwrap();
+    // the ground gets a darker version of the ambient color, as if it was bounced off the floor
+    if args.hemisphere_ambient {
+        ray_marcher.ambient = Ambient::Hemisphere {
+            sky: ray_marcher.bg_light_color,
+            ground: ray_marcher.bg_light_color * 0.3,
+        };
+    }
 
-    println!("Vec size {}", mem::size_of::<Vec3>());
-}
+    if let Some(gi_samples) = args.gi_samples {
+        ray_marcher.gi_samples = gi_samples;
+    }
 
-fn create_image(ray_marcher: RayMarcher) -> RgbImage {
-    // Camera
-    let viewport_height = 2.;
-    let viewport_width = ASPECT_RATIO * viewport_height;
-    let focal_length = 3.;
+    if args.cone_tracing {
+        ray_marcher.cone_tracing = true;
+        ray_marcher.pixel_angle = camera.pixel_angle(settings.height);
+    }
 
-    let origin = Vec3::new(-0.42, 0.05, -0.7);
-    let looking_at = Vec3::new(0.3, -1.6, -2.5);
-    let view_direction = (looking_at - origin).normalize();
+    // an orbit around the point the camera looks at, the frames go into the output directory
+    if let Some(frames) = args.animate {
+        let directory = args.output.clone().unwrap_or_else(|| String::from("frames"));
+        let scene = ray_marcher.scene.clone();
+
+        let timer_start = Instant::now();
+
+        let stats = render_animation(
+            ray_marcher,
+            frames,
+            &settings,
+            &directory,
+            |t| (scene.clone(), orbit_camera(&camera, t)),
+            &|frame, finished, total| report_frame_progress(frame, frames, finished, total),
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("{err}");
+            process::exit(1);
+        });
+
+        println!();
+        println!("Rendered {frames} frames ({}x{}) in {:?}", settings.width, settings.height, timer_start.elapsed());
+        print_stats(&stats);
+        return;
+    }
 
-    // horizontal and vertical vector of the view port
-    let horizontal = Vec3::cross(&view_direction, &Vec3::new(0., 1., 0.)).normalize() * viewport_width;
-    let vertical = -Vec3::cross(&view_direction, &horizontal).normalize() * viewport_height;
+    // the color image is always rendered, the other passes only when they have an output
+    let output = args.output.clone().unwrap_or_else(|| format!("out.{}", args.format.extension()));
+    let passes = [
+        (RenderPass::Color, Some(&output)),
+        (RenderPass::Depth, args.depth.as_ref()),
+        (RenderPass::Normal, args.normal.as_ref()),
+        (RenderPass::Heatmap, args.heatmap.as_ref()),
+        (RenderPass::Edges, args.edges.as_ref()),
+    ];
+
+    for (pass, output) in passes {
+        if let Some(output) = output {
+            let image = render_with_progress(&settings, |progress| {
+                render_pass(&ray_marcher, &camera, &settings, pass, args.format, args.png_alpha, progress)
+            });
+
+            match pass {
+                RenderPass::Color => image.save_with_format(output, args.format.image_format()).unwrap(),
+                _ => image.save(output).unwrap(),
+            }
+        }
+    }
 
-    // lower left corner of the view port
-    let ll_view_corner = -horizontal / 2.0 - vertical / 2.0;
-    // ray direction of the lower left viewport corner
-    let ll_corner = ll_view_corner + view_direction * focal_length;
+    println!("Vec size {}", mem::size_of::<Vec3>());
+}
 
-    // dbg!(ll_corner);
-    // dbg!(ll_corner + horizontal * 1. + vertical * 1.);
+// runs a render with a progress bar on stdout and prints the stats once it's done
+fn render_with_progress<F>(settings: &RenderSettings, render: F) -> DynamicImage
+where
+    F: FnOnce(&(dyn Fn(u32, u32) + Sync)) -> Result<(DynamicImage, RenderStats), String>,
+{
+    let timer_start = Instant::now();
 
-    let mut image = RgbImage::new(IMAGE_WIDTH, IMAGE_HEIGHT);
+    let (image, stats) = render(&report_progress).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        process::exit(1);
+    });
 
-    let timer_start = Instant::now();
+    let timer_duration = timer_start.elapsed();
 
-    let arc_ray_marcher = Arc::new(ray_marcher);
+    println!();
+    println!("Rendered image ({}x{}) in {:?}", settings.width, settings.height, timer_duration);
+    print_stats(&stats);
 
-    // iterate over the pixel rows
-    let pixel_data: Vec<Vec<[u8; 3]>> = (0..IMAGE_HEIGHT).into_par_iter().map(|j| -> Vec<[u8; 3]> {
-        // clone a bunch of stuff into this scope
-        let clone_ray_marcher = arc_ray_marcher.clone();
-        let clone_origin = origin.clone();
-        let clone_ll_corner = ll_corner.clone();
-        let clone_horizontal = horizontal.clone();
-        let clone_vertical = vertical.clone();
+    image
+}
 
-        // iterate over the pixels in the row and calculate their color
-        (0..IMAGE_WIDTH).map(|i| -> [u8; 3] {
-            calc_pixel(clone_ray_marcher.deref(), i, j, &clone_origin, &clone_ll_corner, &clone_horizontal, &clone_vertical)
-        }).collect()
-    }).collect();
+fn print_stats(stats: &RenderStats) {
+    if stats.background_blocks > 0 {
+        println!("Skipped {} of {} background blocks", stats.skipped_blocks, stats.background_blocks);
+    }
 
-    let timer_duration = timer_start.elapsed();
+    println!(
+        "Average iterations {:.2}, hit ratio {:.2}%, {} rays reached max iterations",
+        stats.iterations as Real / stats.rays as Real,
+        stats.hits as Real / stats.rays as Real * 100.,
+        stats.starved,
+    );
+}
 
-    // set the pixel in the actual image
-    for j in 0..IMAGE_HEIGHT {
-        for i in 0..IMAGE_WIDTH {
-            let pixel_color = Rgb(pixel_data[j as usize][i as usize]);
+fn report_progress(finished: u32, total: u32) {
+    // only print when the percentage changes, so the threads rarely touch stdout
+    let percent = finished * 100 / total;
 
-            image.put_pixel(i, IMAGE_HEIGHT - j - 1, pixel_color);
-        }
+    if percent != (finished - 1) * 100 / total {
+        print!("\rRendering... {percent}%");
+        // the progress is only cosmetic, a failed flush shows up with the next line
+        let _ = io::stdout().flush();
     }
+}
 
-    println!("Rendered image ({IMAGE_WIDTH}x{IMAGE_HEIGHT}) in {:?}", timer_duration);
+fn report_frame_progress(frame: u32, frames: u32, finished: u32, total: u32) {
+    let percent = finished * 100 / total;
 
-    image
+    if percent != (finished - 1) * 100 / total {
+        print!("\rFrame {}/{frames}, rendering... {percent}%", frame + 1);
+        let _ = io::stdout().flush();
+    }
 }
 
-fn calc_pixel(rm: &RayMarcher, i: u32, j: u32, origin: &Vec3, ll_corner: &Vec3, horizontal: &Vec3, vertical: &Vec3) -> [u8; 3] {
-    let u = (i as f64) / ((IMAGE_WIDTH - 1) as f64);
-    let v = (j as f64) / ((IMAGE_HEIGHT - 1) as f64);
+fn default_setup() -> (RayMarcher, Camera, RenderSettings) {
+    let julia = DistanceField::Julia(distance_fields::Julia {
+        pos: Vec3::new(0., 0., -1.),
+        iterations: 2000,
+        // -5, 2, 4, -2
+
+        c: Vec4::new(-1.51, 5.9, 4., -2.) / 10.,
+        traps: false,
+        coloring: false,
+        cut: true,
+        cut_y: 0.,
+        bounding_radius: 2.,
+    });
 
-    let pixel_pos = ll_corner + horizontal * u + vertical * v;
+    let ray_marcher = create_ray_marcher(julia);
 
-    let r = Ray::new(&origin, &pixel_pos);
-    let pixel_color = rm.ray_marching(r);
+    let camera = create_camera(Vec3::new(-0.42, 0.05, -0.7), Vec3::new(0.3, -1.6, -2.5));
 
-    pixel_color.to_pixel_data()
-}
\ No newline at end of file
+    (ray_marcher, camera, RenderSettings::default())
+}
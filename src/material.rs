@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+use crate::real::Real;
+use crate::vec3::Vec3;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Material {
+    pub color: Vec3,
+    // index of refraction, materials that have one are transparent and tint the light passing through with color
+    pub ior: Option<Real>,
+}
+
+impl Material {
+    pub fn new(color: Vec3) -> Self {
+        Material {
+            color,
+            ior: None,
+        }
+    }
+}
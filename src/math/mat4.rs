@@ -0,0 +1,186 @@
+use std::ops;
+
+use crate::real::Real;
+use crate::vec3::Vec3;
+
+// row major 4x4 matrix for affine transforms, points are treated as column vectors
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    pub m: [[Real; 4]; 4],
+}
+
+impl Mat4 {
+    pub const fn new(m: [[Real; 4]; 4]) -> Self {
+        Mat4 {
+            m,
+        }
+    }
+
+    pub const fn identity() -> Self {
+        Mat4::new([
+            [1., 0., 0., 0.],
+            [0., 1., 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn translation(offset: &Vec3) -> Self {
+        Mat4::new([
+            [1., 0., 0., offset.x],
+            [0., 1., 0., offset.y],
+            [0., 0., 1., offset.z],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn scale(factor: &Vec3) -> Self {
+        Mat4::new([
+            [factor.x, 0., 0., 0.],
+            [0., factor.y, 0., 0.],
+            [0., 0., factor.z, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    // maps the x, y and z axis onto the given axes and the origin onto origin, the axes are the columns
+    pub fn from_basis(x: &Vec3, y: &Vec3, z: &Vec3, origin: &Vec3) -> Self {
+        Mat4::new([
+            [x.x, y.x, z.x, origin.x],
+            [x.y, y.y, z.y, origin.y],
+            [x.z, y.z, z.z, origin.z],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    // the rotations match Vec3::rotate_x, rotate_y and rotate_z
+    pub fn rotation_x(angle: Real) -> Self {
+        let (sin, cos) = angle.sin_cos();
+
+        Mat4::new([
+            [1., 0., 0., 0.],
+            [0., cos, -sin, 0.],
+            [0., sin, cos, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn rotation_y(angle: Real) -> Self {
+        let (sin, cos) = angle.sin_cos();
+
+        Mat4::new([
+            [cos, 0., sin, 0.],
+            [0., 1., 0., 0.],
+            [-sin, 0., cos, 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    pub fn rotation_z(angle: Real) -> Self {
+        let (sin, cos) = angle.sin_cos();
+
+        Mat4::new([
+            [cos, -sin, 0., 0.],
+            [sin, cos, 0., 0.],
+            [0., 0., 1., 0.],
+            [0., 0., 0., 1.],
+        ])
+    }
+
+    // applies the full transform including the translation
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.m;
+
+        Vec3::new(
+            m[0][0] * p.x + m[0][1] * p.y + m[0][2] * p.z + m[0][3],
+            m[1][0] * p.x + m[1][1] * p.y + m[1][2] * p.z + m[1][3],
+            m[2][0] * p.x + m[2][1] * p.y + m[2][2] * p.z + m[2][3],
+        )
+    }
+
+    // directions are not affected by the translation
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+
+        Vec3::new(
+            m[0][0] * v.x + m[0][1] * v.y + m[0][2] * v.z,
+            m[1][0] * v.x + m[1][1] * v.y + m[1][2] * v.z,
+            m[2][0] * v.x + m[2][1] * v.y + m[2][2] * v.z,
+        )
+    }
+}
+
+fn internal_mul_mat_mat(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut m = [[0.; 4]; 4];
+
+    for (row, m_row) in m.iter_mut().enumerate() {
+        for (col, value) in m_row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a.m[row][k] * b.m[k][col]).sum();
+        }
+    }
+
+    Mat4::new(m)
+}
+
+// a * b applies b first and a second
+impl ops::Mul<Mat4> for Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: Mat4) -> Self::Output {
+        internal_mul_mat_mat(&self, &rhs)
+    }
+}
+
+impl ops::Mul<&Mat4> for &Mat4 {
+    type Output = Mat4;
+
+    fn mul(self, rhs: &Mat4) -> Self::Output {
+        internal_mul_mat_mat(self, rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::real::consts;
+
+    fn approx_eq(a: &Mat4, b: &Mat4, eps: Real) -> bool {
+        a.m.iter().flatten().zip(b.m.iter().flatten()).all(|(x, y)| (x - y).abs() < eps)
+    }
+
+    #[test]
+    fn multiplication_is_associative() {
+        let a = Mat4::rotation_x(0.3) * Mat4::translation(&Vec3::new(1., 2., 3.));
+        let b = Mat4::scale(&Vec3::new(2., 0.5, 1.));
+        let c = Mat4::rotation_y(1.1) * Mat4::rotation_z(-0.4);
+
+        assert!(approx_eq(&((a * b) * c), &(a * (b * c)), 1e-6));
+        assert_eq!(a * Mat4::identity(), a);
+    }
+
+    #[test]
+    fn transforms_points_and_vectors() {
+        let m = Mat4::translation(&Vec3::new(1., 2., 3.)) * Mat4::rotation_z(consts::FRAC_PI_2);
+
+        // the rotation is applied before the translation
+        assert!(m.transform_point(&Vec3::new(1., 0., 0.)).approx_eq(&Vec3::new(1., 3., 3.), 1e-6));
+        // vectors only rotate
+        assert!(m.transform_vector(&Vec3::new(1., 0., 0.)).approx_eq(&Vec3::new(0., 1., 0.), 1e-6));
+    }
+
+    #[test]
+    fn rotations_match_the_vec3_helpers() {
+        let p = Vec3::new(0.3, -1.2, 2.5);
+
+        assert!(Mat4::rotation_x(0.7).transform_point(&p).approx_eq(&p.rotate_x(0.7), 1e-6));
+        assert!(Mat4::rotation_y(0.7).transform_point(&p).approx_eq(&p.rotate_y(0.7), 1e-6));
+        assert!(Mat4::rotation_z(0.7).transform_point(&p).approx_eq(&p.rotate_z(0.7), 1e-6));
+    }
+
+    #[test]
+    fn from_basis_maps_the_axes() {
+        let m = Mat4::from_basis(&Vec3::new(0., 0., -1.), &Vec3::new(0., 1., 0.), &Vec3::new(1., 0., 0.), &Vec3::new(5., 0., 0.));
+
+        assert!(m.transform_point(&Vec3::new(1., 2., 3.)).approx_eq(&Vec3::new(8., 2., -1.), 1e-6));
+    }
+}
@@ -0,0 +1,290 @@
+use std::ops;
+
+use serde::{Deserialize, Serialize};
+
+use crate::real::widen;
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+
+// q = r + i·i + j·j + k·k, always in f64 since it's used by the fractals that need the precision
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Quaternion {
+    pub r: f64,
+    pub i: f64,
+    pub j: f64,
+    pub k: f64,
+}
+
+impl Quaternion {
+    pub const fn new(r: f64, i: f64, j: f64, k: f64) -> Self {
+        Quaternion {
+            r,
+            i,
+            j,
+            k,
+        }
+    }
+
+    pub const fn zero() -> Self {
+        Quaternion::new(0., 0., 0., 0.)
+    }
+
+    pub const fn identity() -> Self {
+        Quaternion::new(1., 0., 0., 0.)
+    }
+
+    // a pure quaternion with the vector as the imaginary part
+    pub fn from_vec3(vec3: &Vec3) -> Self {
+        Quaternion::new(0., widen(vec3.x), widen(vec3.y), widen(vec3.z))
+    }
+
+    pub fn sqr_length(&self) -> f64 {
+        self.r * self.r + self.i * self.i + self.j * self.j + self.k * self.k
+    }
+
+    pub fn length(&self) -> f64 {
+        self.sqr_length().sqrt()
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Quaternion::new(self.r, -self.i, -self.j, -self.k)
+    }
+
+    // q², cheaper than q * q since the imaginary parts commute with themselves
+    pub fn square(&self) -> Self {
+        Quaternion {
+            r: self.r * self.r - self.i * self.i - self.j * self.j - self.k * self.k,
+            i: self.r * self.i * 2.,
+            j: self.r * self.j * 2.,
+            k: self.r * self.k * 2.,
+        }
+    }
+
+    // q³, expanded the same way as square
+    pub fn cube(&self) -> Self {
+        let r2 = self.r * self.r;
+        let imaginary2 = self.i * self.i + self.j * self.j + self.k * self.k;
+        let r_factor = r2 - imaginary2 * 3.;
+        let imaginary_factor = r2 * 3. - imaginary2;
+
+        Quaternion {
+            r: self.r * r_factor,
+            i: self.i * imaginary_factor,
+            j: self.j * imaginary_factor,
+            k: self.k * imaginary_factor,
+        }
+    }
+
+    pub fn approx_eq(&self, other: &Self, eps: f64) -> bool {
+        (self.r - other.r).abs() <= eps && (self.i - other.i).abs() <= eps && (self.j - other.j).abs() <= eps && (self.k - other.k).abs() <= eps
+    }
+}
+
+// --- CONVERSIONS ---
+
+// x is the real part, y, z and w the imaginary ones
+impl From<Vec4> for Quaternion {
+    fn from(v: Vec4) -> Self {
+        Quaternion::new(widen(v.x), widen(v.y), widen(v.z), widen(v.w))
+    }
+}
+
+impl From<&Vec4> for Quaternion {
+    fn from(v: &Vec4) -> Self {
+        Quaternion::new(widen(v.x), widen(v.y), widen(v.z), widen(v.w))
+    }
+}
+
+fn internal_add_quat_quat(a: &Quaternion, b: &Quaternion) -> Quaternion {
+    Quaternion::new(a.r + b.r, a.i + b.i, a.j + b.j, a.k + b.k)
+}
+
+fn internal_sub_quat_quat(a: &Quaternion, b: &Quaternion) -> Quaternion {
+    Quaternion::new(a.r - b.r, a.i - b.i, a.j - b.j, a.k - b.k)
+}
+
+// hamilton product
+fn internal_mul_quat_quat(a: &Quaternion, b: &Quaternion) -> Quaternion {
+    Quaternion {
+        r: a.r * b.r - a.i * b.i - a.j * b.j - a.k * b.k,
+        i: a.r * b.i + a.i * b.r + a.j * b.k - a.k * b.j,
+        j: a.r * b.j - a.i * b.k + a.j * b.r + a.k * b.i,
+        k: a.r * b.k + a.i * b.j - a.j * b.i + a.k * b.r,
+    }
+}
+
+fn internal_mul_quat_scalar(a: &Quaternion, b: f64) -> Quaternion {
+    Quaternion::new(a.r * b, a.i * b, a.j * b, a.k * b)
+}
+
+// --- ADD ---
+
+impl ops::Add<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, rhs: Quaternion) -> Self::Output {
+        internal_add_quat_quat(&self, &rhs)
+    }
+}
+
+impl ops::Add<Quaternion> for &Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, rhs: Quaternion) -> Self::Output {
+        internal_add_quat_quat(self, &rhs)
+    }
+}
+
+impl ops::Add<&Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, rhs: &Quaternion) -> Self::Output {
+        internal_add_quat_quat(&self, rhs)
+    }
+}
+
+impl ops::Add<&Quaternion> for &Quaternion {
+    type Output = Quaternion;
+
+    fn add(self, rhs: &Quaternion) -> Self::Output {
+        internal_add_quat_quat(self, rhs)
+    }
+}
+
+// --- SUB ---
+
+impl ops::Sub<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn sub(self, rhs: Quaternion) -> Self::Output {
+        internal_sub_quat_quat(&self, &rhs)
+    }
+}
+
+impl ops::Sub<Quaternion> for &Quaternion {
+    type Output = Quaternion;
+
+    fn sub(self, rhs: Quaternion) -> Self::Output {
+        internal_sub_quat_quat(self, &rhs)
+    }
+}
+
+impl ops::Sub<&Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn sub(self, rhs: &Quaternion) -> Self::Output {
+        internal_sub_quat_quat(&self, rhs)
+    }
+}
+
+impl ops::Sub<&Quaternion> for &Quaternion {
+    type Output = Quaternion;
+
+    fn sub(self, rhs: &Quaternion) -> Self::Output {
+        internal_sub_quat_quat(self, rhs)
+    }
+}
+
+// --- MUL ---
+
+impl ops::Mul<Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Self::Output {
+        internal_mul_quat_quat(&self, &rhs)
+    }
+}
+
+impl ops::Mul<Quaternion> for &Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Self::Output {
+        internal_mul_quat_quat(self, &rhs)
+    }
+}
+
+impl ops::Mul<&Quaternion> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: &Quaternion) -> Self::Output {
+        internal_mul_quat_quat(&self, rhs)
+    }
+}
+
+impl ops::Mul<&Quaternion> for &Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: &Quaternion) -> Self::Output {
+        internal_mul_quat_quat(self, rhs)
+    }
+}
+
+impl ops::Mul<f64> for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        internal_mul_quat_scalar(&self, rhs)
+    }
+}
+
+impl ops::Mul<f64> for &Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        internal_mul_quat_scalar(self, rhs)
+    }
+}
+
+// --- NEG ---
+
+impl ops::Neg for Quaternion {
+    type Output = Quaternion;
+
+    fn neg(self) -> Self::Output {
+        internal_mul_quat_scalar(&self, -1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_and_cube_match_the_hamilton_product() {
+        for q in [Quaternion::new(1., 2., 3., 4.), Quaternion::new(-0.5, 0.25, -1.5, 0.75), Quaternion::new(0., 0., 1., 0.)] {
+            assert!((q * q).approx_eq(&q.square(), 1e-12));
+            assert!((q * q * q).approx_eq(&q.cube(), 1e-12));
+        }
+    }
+
+    #[test]
+    fn hamilton_product_of_the_units() {
+        let i = Quaternion::new(0., 1., 0., 0.);
+        let j = Quaternion::new(0., 0., 1., 0.);
+        let k = Quaternion::new(0., 0., 0., 1.);
+
+        // i² = j² = k² = ijk = -1
+        for q in [i * i, j * j, k * k, i * j * k] {
+            assert!(q.approx_eq(&-Quaternion::identity(), 0.));
+        }
+
+        // the product doesn't commute
+        assert!((i * j).approx_eq(&k, 0.));
+        assert!((j * i).approx_eq(&-k, 0.));
+    }
+
+    #[test]
+    fn conjugate_gives_the_squared_length() {
+        let q = Quaternion::new(1., 2., 3., 4.);
+
+        assert_eq!(q.sqr_length(), 30.);
+        assert!((q * q.conjugate()).approx_eq(&Quaternion::new(30., 0., 0., 0.), 0.));
+    }
+
+    #[test]
+    fn converts_from_vec4_with_x_as_the_real_part() {
+        let q = Quaternion::from(Vec4::new(1., 2., 3., 4.));
+
+        assert!(q.approx_eq(&Quaternion::new(1., 2., 3., 4.), 0.));
+    }
+}
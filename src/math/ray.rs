@@ -9,7 +9,7 @@ pub struct Ray {
 impl Ray {
     pub fn new(orig: &Vec3, dir: &Vec3) -> Self {
         Ray {
-            orig: orig.clone(),
+            orig: *orig,
             dir: dir.normalize(),
         }
     }
@@ -0,0 +1,22 @@
+// the floating point type of the whole pipeline, the f32 feature trades precision for speed
+#[cfg(not(feature = "f32"))]
+pub type Real = f64;
+#[cfg(feature = "f32")]
+pub type Real = f32;
+
+#[cfg(not(feature = "f32"))]
+pub use std::f64::consts;
+#[cfg(feature = "f32")]
+pub use std::f32::consts;
+
+// for the code that always needs the precision of f64, like the iteration of some fractals
+#[allow(clippy::useless_conversion)]
+pub fn widen(x: Real) -> f64 {
+    f64::from(x)
+}
+
+// the way back from widen, this rounds with the f32 feature
+#[allow(clippy::unnecessary_cast)]
+pub fn narrow(x: f64) -> Real {
+    x as Real
+}
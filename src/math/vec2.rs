@@ -0,0 +1,327 @@
+use std::ops;
+
+use serde::{Deserialize, Serialize};
+
+use crate::real::Real;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Vec2 {
+    pub x: Real,
+    pub y: Real,
+}
+
+impl Vec2 {
+    pub const fn new(x: Real, y: Real) -> Self {
+        Vec2 {
+            x,
+            y,
+        }
+    }
+
+    pub const fn one() -> Self {
+        Vec2::new(1., 1.)
+    }
+
+    pub const fn zero() -> Self {
+        Vec2::new(0., 0.)
+    }
+
+    pub fn dot(a: &Self, b: &Self) -> Real {
+        a.x * b.x + a.y * b.y
+    }
+
+    pub fn sqr_length(&self) -> Real {
+        Vec2::dot(self, self)
+    }
+
+    pub fn length(&self) -> Real {
+        self.sqr_length().sqrt()
+    }
+
+    pub fn normalize(&self) -> Self {
+        self / self.length()
+    }
+
+    pub fn abs(&self) -> Self {
+        Vec2 {
+            x: self.x.abs(),
+            y: self.y.abs(),
+        }
+    }
+
+    pub fn min(a: &Self, b: Real) -> Self {
+        Vec2 {
+            x: a.x.min(b),
+            y: a.y.min(b),
+        }
+    }
+
+    pub fn max(a: &Self, b: Real) -> Self {
+        Vec2 {
+            x: a.x.max(b),
+            y: a.y.max(b),
+        }
+    }
+
+    pub fn max_element(&self) -> Real {
+        self.x.max(self.y)
+    }
+
+    pub fn min_element(&self) -> Real {
+        self.x.min(self.y)
+    }
+}
+
+fn internal_add_vec_vec(a: &Vec2, b: &Vec2) -> Vec2 {
+    Vec2::new(a.x + b.x, a.y + b.y)
+}
+
+fn internal_add_vec_scalar(a: &Vec2, b: Real) -> Vec2 {
+    Vec2::new(a.x + b, a.y + b)
+}
+
+fn internal_sub_vec_vec(a: &Vec2, b: &Vec2) -> Vec2 {
+    Vec2::new(a.x - b.x, a.y - b.y)
+}
+
+fn internal_sub_vec_scalar(a: &Vec2, b: Real) -> Vec2 {
+    Vec2::new(a.x - b, a.y - b)
+}
+
+fn internal_mul_vec_vec(a: &Vec2, b: &Vec2) -> Vec2 {
+    Vec2::new(a.x * b.x, a.y * b.y)
+}
+
+fn internal_mul_vec_scalar(a: &Vec2, b: Real) -> Vec2 {
+    Vec2::new(a.x * b, a.y * b)
+}
+
+fn internal_div_vec_vec(a: &Vec2, b: &Vec2) -> Vec2 {
+    Vec2::new(a.x / b.x, a.y / b.y)
+}
+
+fn internal_div_vec_scalar(a: &Vec2, b: Real) -> Vec2 {
+    Vec2::new(a.x / b, a.y / b)
+}
+
+fn internal_neg_vec(a: &Vec2) -> Vec2 {
+    Vec2::new(-a.x, -a.y)
+}
+
+// --- ADD ---
+
+impl ops::Add<Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, rhs: Vec2) -> Self::Output {
+        internal_add_vec_vec(&self, &rhs)
+    }
+}
+
+impl ops::Add<Vec2> for &Vec2 {
+    type Output = Vec2;
+
+    fn add(self, rhs: Vec2) -> Self::Output {
+        internal_add_vec_vec(self, &rhs)
+    }
+}
+
+impl ops::Add<&Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, rhs: &Vec2) -> Self::Output {
+        internal_add_vec_vec(&self, rhs)
+    }
+}
+
+impl ops::Add<&Vec2> for &Vec2 {
+    type Output = Vec2;
+
+    fn add(self, rhs: &Vec2) -> Self::Output {
+        internal_add_vec_vec(self, rhs)
+    }
+}
+
+impl ops::Add<Real> for Vec2 {
+    type Output = Vec2;
+
+    fn add(self, rhs: Real) -> Self::Output {
+        internal_add_vec_scalar(&self, rhs)
+    }
+}
+
+impl ops::Add<Real> for &Vec2 {
+    type Output = Vec2;
+
+    fn add(self, rhs: Real) -> Self::Output {
+        internal_add_vec_scalar(self, rhs)
+    }
+}
+
+// --- SUB ---
+
+impl ops::Sub<Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, rhs: Vec2) -> Self::Output {
+        internal_sub_vec_vec(&self, &rhs)
+    }
+}
+
+impl ops::Sub<Vec2> for &Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, rhs: Vec2) -> Self::Output {
+        internal_sub_vec_vec(self, &rhs)
+    }
+}
+
+impl ops::Sub<&Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, rhs: &Vec2) -> Self::Output {
+        internal_sub_vec_vec(&self, rhs)
+    }
+}
+
+impl ops::Sub<&Vec2> for &Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, rhs: &Vec2) -> Self::Output {
+        internal_sub_vec_vec(self, rhs)
+    }
+}
+
+impl ops::Sub<Real> for Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, rhs: Real) -> Self::Output {
+        internal_sub_vec_scalar(&self, rhs)
+    }
+}
+
+impl ops::Sub<Real> for &Vec2 {
+    type Output = Vec2;
+
+    fn sub(self, rhs: Real) -> Self::Output {
+        internal_sub_vec_scalar(self, rhs)
+    }
+}
+
+// --- MUL ---
+
+impl ops::Mul<Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, rhs: Vec2) -> Self::Output {
+        internal_mul_vec_vec(&self, &rhs)
+    }
+}
+
+impl ops::Mul<Vec2> for &Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, rhs: Vec2) -> Self::Output {
+        internal_mul_vec_vec(self, &rhs)
+    }
+}
+
+impl ops::Mul<&Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, rhs: &Vec2) -> Self::Output {
+        internal_mul_vec_vec(&self, rhs)
+    }
+}
+
+impl ops::Mul<&Vec2> for &Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, rhs: &Vec2) -> Self::Output {
+        internal_mul_vec_vec(self, rhs)
+    }
+}
+
+impl ops::Mul<Real> for Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, rhs: Real) -> Self::Output {
+        internal_mul_vec_scalar(&self, rhs)
+    }
+}
+
+impl ops::Mul<Real> for &Vec2 {
+    type Output = Vec2;
+
+    fn mul(self, rhs: Real) -> Self::Output {
+        internal_mul_vec_scalar(self, rhs)
+    }
+}
+
+// --- DIV ---
+
+impl ops::Div<Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn div(self, rhs: Vec2) -> Self::Output {
+        internal_div_vec_vec(&self, &rhs)
+    }
+}
+
+impl ops::Div<Vec2> for &Vec2 {
+    type Output = Vec2;
+
+    fn div(self, rhs: Vec2) -> Self::Output {
+        internal_div_vec_vec(self, &rhs)
+    }
+}
+
+impl ops::Div<&Vec2> for Vec2 {
+    type Output = Vec2;
+
+    fn div(self, rhs: &Vec2) -> Self::Output {
+        internal_div_vec_vec(&self, rhs)
+    }
+}
+
+impl ops::Div<&Vec2> for &Vec2 {
+    type Output = Vec2;
+
+    fn div(self, rhs: &Vec2) -> Self::Output {
+        internal_div_vec_vec(self, rhs)
+    }
+}
+
+impl ops::Div<Real> for Vec2 {
+    type Output = Vec2;
+
+    fn div(self, rhs: Real) -> Self::Output {
+        internal_div_vec_scalar(&self, rhs)
+    }
+}
+
+impl ops::Div<Real> for &Vec2 {
+    type Output = Vec2;
+
+    fn div(self, rhs: Real) -> Self::Output {
+        internal_div_vec_scalar(self, rhs)
+    }
+}
+
+// --- NEG ---
+
+impl ops::Neg for Vec2 {
+    type Output = Vec2;
+
+    fn neg(self) -> Self::Output {
+        internal_neg_vec(&self)
+    }
+}
+
+impl ops::Neg for &Vec2 {
+    type Output = Vec2;
+
+    fn neg(self) -> Self::Output {
+        internal_neg_vec(self)
+    }
+}
@@ -1,17 +1,23 @@
 use std::ops;
 
 use num::clamp;
-use num::integer::Roots;
+use serde::{Deserialize, Serialize};
+#[cfg(all(feature = "simd", not(feature = "f32")))]
+use wide::f64x4 as Lanes;
+#[cfg(all(feature = "simd", feature = "f32"))]
+use wide::f32x4 as Lanes;
 
-#[derive(Debug, Clone, Copy)]
+use crate::real::Real;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Vec3 {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
+    pub x: Real,
+    pub y: Real,
+    pub z: Real,
 }
 
 impl Vec3 {
-    pub const fn new(x: f64, y: f64, z: f64) -> Self {
+    pub const fn new(x: Real, y: Real, z: Real) -> Self {
         Vec3 {
             x,
             y,
@@ -27,10 +33,18 @@ impl Vec3 {
         Vec3::new(0., 0., 0.)
     }
 
-    pub fn dot(a: &Self, b: &Self) -> f64 {
+    #[cfg(not(feature = "simd"))]
+    pub fn dot(a: &Self, b: &Self) -> Real {
         a.x * b.x + a.y * b.y + a.z * b.z
     }
 
+    // summed in the same order as the scalar version, so both give the same results
+    #[cfg(feature = "simd")]
+    pub fn dot(a: &Self, b: &Self) -> Real {
+        let [x, y, z, _] = (to_lanes(a) * to_lanes(b)).to_array();
+        x + y + z
+    }
+
     pub fn cross(a: &Self, b: &Self) -> Vec3 {
         Vec3 {
             x: a.y*b.z - a.z*b.y,
@@ -39,11 +53,11 @@ impl Vec3 {
         }
     }
 
-    pub fn sqr_length(&self) -> f64 {
+    pub fn sqr_length(&self) -> Real {
         Vec3::dot(self, self)
     }
 
-    pub fn length(&self) -> f64 {
+    pub fn length(&self) -> Real {
         self.sqr_length().sqrt()
     }
 
@@ -59,7 +73,7 @@ impl Vec3 {
         }
     }
 
-    pub fn min(a: &Self, b: f64) -> Self {
+    pub fn min(a: &Self, b: Real) -> Self {
         Vec3 {
             x: a.x.min(b),
             y: a.y.min(b),
@@ -67,7 +81,7 @@ impl Vec3 {
         }
     }
 
-    pub fn max(a: &Self, b: f64) -> Self {
+    pub fn max(a: &Self, b: Real) -> Self {
         Vec3 {
             x: a.x.max(b),
             y: a.y.max(b),
@@ -75,59 +89,191 @@ impl Vec3 {
         }
     }
 
-    pub fn max_element(&self) -> f64 {
-        return self.x.max(self.y).max(self.z);
+    pub fn max_element(&self) -> Real {
+        self.x.max(self.y).max(self.z)
     }
 
-    pub fn min_element(&self) -> f64 {
-        return self.x.min(self.y).min(self.z);
+    pub fn min_element(&self) -> Real {
+        self.x.min(self.y).min(self.z)
     }
 
-    pub fn clamp(&self, min: f64, max: f64) -> Self {
+    pub fn clamp(&self, min: Real, max: Real) -> Self {
         Vec3 {
             x: clamp(self.x, min, max),
             y: clamp(self.y, min, max),
             z: clamp(self.z, min, max),
         }
     }
+
+    // reflects the incident direction on the plane with the given unit normal
+    pub fn reflect(incident: &Self, normal: &Self) -> Vec3 {
+        incident - normal * (2. * Vec3::dot(incident, normal))
+    }
+
+    // refracts the incident direction with the ratio of indices of refraction eta (snell's law),
+    // None on total internal reflection
+    pub fn refract(incident: &Self, normal: &Self, eta: Real) -> Option<Vec3> {
+        let cos_i = Vec3::dot(normal, incident);
+        let k = 1. - eta * eta * (1. - cos_i * cos_i);
+        if k < 0. {
+            return None;
+        }
+
+        Some(incident * eta - normal * (eta * cos_i + k.sqrt()))
+    }
+
+    pub fn lerp(a: &Self, b: &Self, t: Real) -> Vec3 {
+        a + (b - a) * t
+    }
+
+    pub fn rotate_x(&self, angle: Real) -> Self {
+        let (sin, cos) = angle.sin_cos();
+
+        Vec3 {
+            x: self.x,
+            y: self.y * cos - self.z * sin,
+            z: self.y * sin + self.z * cos,
+        }
+    }
+
+    pub fn rotate_y(&self, angle: Real) -> Self {
+        let (sin, cos) = angle.sin_cos();
+
+        Vec3 {
+            x: self.x * cos + self.z * sin,
+            y: self.y,
+            z: -self.x * sin + self.z * cos,
+        }
+    }
+
+    pub fn rotate_z(&self, angle: Real) -> Self {
+        let (sin, cos) = angle.sin_cos();
+
+        Vec3 {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+            z: self.z,
+        }
+    }
+
+    // rotates around an arbitrary axis (rodrigues' rotation formula)
+    pub fn rotate(v: &Self, axis: &Self, angle: Real) -> Vec3 {
+        let axis = axis.normalize();
+        let (sin, cos) = angle.sin_cos();
+
+        v * cos + Vec3::cross(&axis, v) * sin + axis * (Vec3::dot(&axis, v) * (1. - cos))
+    }
+
+    // component wise comparison with a tolerance, meant for tests and tolerance sensitive code, not for hashing
+    pub fn approx_eq(&self, other: &Self, eps: Real) -> bool {
+        (self.x - other.x).abs() <= eps
+            && (self.y - other.y).abs() <= eps
+            && (self.z - other.z).abs() <= eps
+    }
 }
 
+#[cfg(not(feature = "simd"))]
 fn internal_add_vec_vec(a: &Vec3, b: &Vec3) -> Vec3 {
     Vec3::new(a.x + b.x, a.y + b.y, a.z + b.z)
 }
 
-fn internal_add_vec_scalar(a: &Vec3, b: f64) -> Vec3 {
+#[cfg(not(feature = "simd"))]
+fn internal_add_vec_scalar(a: &Vec3, b: Real) -> Vec3 {
     Vec3::new(a.x + b, a.y + b, a.z + b)
 }
 
+#[cfg(not(feature = "simd"))]
 fn internal_sub_vec_vec(a: &Vec3, b: &Vec3) -> Vec3 {
     Vec3::new(a.x - b.x, a.y - b.y, a.z - b.z)
 }
 
-fn internal_sub_vec_scalar(a: &Vec3, b: f64) -> Vec3 {
+#[cfg(not(feature = "simd"))]
+fn internal_sub_vec_scalar(a: &Vec3, b: Real) -> Vec3 {
     Vec3::new(a.x - b, a.y - b, a.z - b)
 }
 
+#[cfg(not(feature = "simd"))]
 fn internal_mul_vec_vec(a: &Vec3, b: &Vec3) -> Vec3 {
     Vec3::new(a.x * b.x, a.y * b.y, a.z * b.z)
 }
 
-fn internal_mul_vec_scalar(a: &Vec3, b: f64) -> Vec3 {
+#[cfg(not(feature = "simd"))]
+fn internal_mul_vec_scalar(a: &Vec3, b: Real) -> Vec3 {
     Vec3::new(a.x * b, a.y * b, a.z * b)
 }
 
+#[cfg(not(feature = "simd"))]
 fn internal_div_vec_vec(a: &Vec3, b: &Vec3) -> Vec3 {
     Vec3::new(a.x / b.x, a.y / b.y, a.z / b.z)
 }
 
-fn internal_div_vec_scalar(a: &Vec3, b: f64) -> Vec3 {
+#[cfg(not(feature = "simd"))]
+fn internal_div_vec_scalar(a: &Vec3, b: Real) -> Vec3 {
     Vec3::new(a.x / b, a.y / b, a.z / b)
 }
 
+#[cfg(not(feature = "simd"))]
 fn internal_neg_vec(a: &Vec3) -> Vec3 {
     Vec3::new(-a.x, -a.y, -a.z)
 }
 
+// with the simd feature the arithmetic runs on 4 lanes, the fourth lane is unused padding
+#[cfg(feature = "simd")]
+fn to_lanes(a: &Vec3) -> Lanes {
+    Lanes::new([a.x, a.y, a.z, 0.])
+}
+
+#[cfg(feature = "simd")]
+fn from_lanes(lanes: Lanes) -> Vec3 {
+    let [x, y, z, _] = lanes.to_array();
+    Vec3::new(x, y, z)
+}
+
+#[cfg(feature = "simd")]
+fn internal_add_vec_vec(a: &Vec3, b: &Vec3) -> Vec3 {
+    from_lanes(to_lanes(a) + to_lanes(b))
+}
+
+#[cfg(feature = "simd")]
+fn internal_add_vec_scalar(a: &Vec3, b: Real) -> Vec3 {
+    from_lanes(to_lanes(a) + Lanes::splat(b))
+}
+
+#[cfg(feature = "simd")]
+fn internal_sub_vec_vec(a: &Vec3, b: &Vec3) -> Vec3 {
+    from_lanes(to_lanes(a) - to_lanes(b))
+}
+
+#[cfg(feature = "simd")]
+fn internal_sub_vec_scalar(a: &Vec3, b: Real) -> Vec3 {
+    from_lanes(to_lanes(a) - Lanes::splat(b))
+}
+
+#[cfg(feature = "simd")]
+fn internal_mul_vec_vec(a: &Vec3, b: &Vec3) -> Vec3 {
+    from_lanes(to_lanes(a) * to_lanes(b))
+}
+
+#[cfg(feature = "simd")]
+fn internal_mul_vec_scalar(a: &Vec3, b: Real) -> Vec3 {
+    from_lanes(to_lanes(a) * Lanes::splat(b))
+}
+
+#[cfg(feature = "simd")]
+fn internal_div_vec_vec(a: &Vec3, b: &Vec3) -> Vec3 {
+    from_lanes(to_lanes(a) / to_lanes(b))
+}
+
+#[cfg(feature = "simd")]
+fn internal_div_vec_scalar(a: &Vec3, b: Real) -> Vec3 {
+    from_lanes(to_lanes(a) / Lanes::splat(b))
+}
+
+#[cfg(feature = "simd")]
+fn internal_neg_vec(a: &Vec3) -> Vec3 {
+    from_lanes(-to_lanes(a))
+}
+
 // --- ADD ---
 
 impl ops::Add<Vec3> for Vec3 {
@@ -142,7 +288,7 @@ impl ops::Add<Vec3> for &Vec3 {
     type Output = Vec3;
 
     fn add(self, rhs: Vec3) -> Self::Output {
-        internal_add_vec_vec(&self, &rhs)
+        internal_add_vec_vec(self, &rhs)
     }
 }
 
@@ -150,7 +296,7 @@ impl ops::Add<&Vec3> for Vec3 {
     type Output = Vec3;
 
     fn add(self, rhs: &Vec3) -> Self::Output {
-        internal_add_vec_vec(&self, &rhs)
+        internal_add_vec_vec(&self, rhs)
     }
 }
 
@@ -158,23 +304,23 @@ impl ops::Add<&Vec3> for &Vec3 {
     type Output = Vec3;
 
     fn add(self, rhs: &Vec3) -> Self::Output {
-        internal_add_vec_vec(&self, &rhs)
+        internal_add_vec_vec(self, rhs)
     }
 }
 
-impl ops::Add<f64> for Vec3 {
+impl ops::Add<Real> for Vec3 {
     type Output = Vec3;
 
-    fn add(self, rhs: f64) -> Self::Output {
+    fn add(self, rhs: Real) -> Self::Output {
         internal_add_vec_scalar(&self, rhs)
     }
 }
 
-impl ops::Add<f64> for &Vec3 {
+impl ops::Add<Real> for &Vec3 {
     type Output = Vec3;
 
-    fn add(self, rhs: f64) -> Self::Output {
-        internal_add_vec_scalar(&self, rhs)
+    fn add(self, rhs: Real) -> Self::Output {
+        internal_add_vec_scalar(self, rhs)
     }
 }
 
@@ -192,7 +338,7 @@ impl ops::Sub<Vec3> for &Vec3 {
     type Output = Vec3;
 
     fn sub(self, rhs: Vec3) -> Self::Output {
-        internal_sub_vec_vec(&self, &rhs)
+        internal_sub_vec_vec(self, &rhs)
     }
 }
 
@@ -200,7 +346,7 @@ impl ops::Sub<&Vec3> for Vec3 {
     type Output = Vec3;
 
     fn sub(self, rhs: &Vec3) -> Self::Output {
-        internal_sub_vec_vec(&self, &rhs)
+        internal_sub_vec_vec(&self, rhs)
     }
 }
 
@@ -208,23 +354,23 @@ impl ops::Sub<&Vec3> for &Vec3 {
     type Output = Vec3;
 
     fn sub(self, rhs: &Vec3) -> Self::Output {
-        internal_sub_vec_vec(&self, &rhs)
+        internal_sub_vec_vec(self, rhs)
     }
 }
 
-impl ops::Sub<f64> for Vec3 {
+impl ops::Sub<Real> for Vec3 {
     type Output = Vec3;
 
-    fn sub(self, rhs: f64) -> Self::Output {
+    fn sub(self, rhs: Real) -> Self::Output {
         internal_sub_vec_scalar(&self, rhs)
     }
 }
 
-impl ops::Sub<f64> for &Vec3 {
+impl ops::Sub<Real> for &Vec3 {
     type Output = Vec3;
 
-    fn sub(self, rhs: f64) -> Self::Output {
-        internal_sub_vec_scalar(&self, rhs)
+    fn sub(self, rhs: Real) -> Self::Output {
+        internal_sub_vec_scalar(self, rhs)
     }
 }
 
@@ -242,37 +388,53 @@ impl ops::Mul<Vec3> for &Vec3 {
     type Output = Vec3;
 
     fn mul(self, rhs: Vec3) -> Self::Output {
-        internal_mul_vec_vec(&self, &rhs)
+        internal_mul_vec_vec(self, &rhs)
     }
 }
 impl ops::Mul<&Vec3> for Vec3 {
     type Output = Vec3;
 
     fn mul(self, rhs: &Vec3) -> Self::Output {
-        internal_mul_vec_vec(&self, &rhs)
+        internal_mul_vec_vec(&self, rhs)
     }
 }
 impl ops::Mul<&Vec3> for &Vec3 {
     type Output = Vec3;
 
     fn mul(self, rhs: &Vec3) -> Self::Output {
-        internal_mul_vec_vec(&self, &rhs)
+        internal_mul_vec_vec(self, rhs)
     }
 }
 
-impl ops::Mul<f64> for Vec3 {
+impl ops::Mul<Real> for Vec3 {
     type Output = Vec3;
 
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Real) -> Self::Output {
         internal_mul_vec_scalar(&self, rhs)
     }
 }
 
-impl ops::Mul<f64> for &Vec3 {
+impl ops::Mul<Real> for &Vec3 {
     type Output = Vec3;
 
-    fn mul(self, rhs: f64) -> Self::Output {
-        internal_mul_vec_scalar(&self, rhs)
+    fn mul(self, rhs: Real) -> Self::Output {
+        internal_mul_vec_scalar(self, rhs)
+    }
+}
+
+impl ops::Mul<Vec3> for Real {
+    type Output = Vec3;
+
+    fn mul(self, rhs: Vec3) -> Self::Output {
+        internal_mul_vec_scalar(&rhs, self)
+    }
+}
+
+impl ops::Mul<&Vec3> for Real {
+    type Output = Vec3;
+
+    fn mul(self, rhs: &Vec3) -> Self::Output {
+        internal_mul_vec_scalar(rhs, self)
     }
 }
 
@@ -290,7 +452,7 @@ impl ops::Div<Vec3> for &Vec3 {
     type Output = Vec3;
 
     fn div(self, rhs: Vec3) -> Self::Output {
-        internal_div_vec_vec(&self, &rhs)
+        internal_div_vec_vec(self, &rhs)
     }
 }
 
@@ -298,7 +460,7 @@ impl ops::Div<&Vec3> for Vec3 {
     type Output = Vec3;
 
     fn div(self, rhs: &Vec3) -> Self::Output {
-        internal_div_vec_vec(&self, &rhs)
+        internal_div_vec_vec(&self, rhs)
     }
 }
 
@@ -306,23 +468,55 @@ impl ops::Div<&Vec3> for &Vec3 {
     type Output = Vec3;
 
     fn div(self, rhs: &Vec3) -> Self::Output {
-        internal_div_vec_vec(&self, &rhs)
+        internal_div_vec_vec(self, rhs)
     }
 }
 
-impl ops::Div<f64> for Vec3 {
+impl ops::Div<Real> for Vec3 {
     type Output = Vec3;
 
-    fn div(self, rhs: f64) -> Self::Output {
+    fn div(self, rhs: Real) -> Self::Output {
         internal_div_vec_scalar(&self, rhs)
     }
 }
 
-impl ops::Div<f64> for &Vec3 {
+impl ops::Div<Real> for &Vec3 {
     type Output = Vec3;
 
-    fn div(self, rhs: f64) -> Self::Output {
-        internal_div_vec_scalar(&self, rhs)
+    fn div(self, rhs: Real) -> Self::Output {
+        internal_div_vec_scalar(self, rhs)
+    }
+}
+
+// --- ASSIGN ---
+
+impl ops::AddAssign<Vec3> for Vec3 {
+    fn add_assign(&mut self, rhs: Vec3) {
+        *self = internal_add_vec_vec(self, &rhs);
+    }
+}
+
+impl ops::AddAssign<&Vec3> for Vec3 {
+    fn add_assign(&mut self, rhs: &Vec3) {
+        *self = internal_add_vec_vec(self, rhs);
+    }
+}
+
+impl ops::SubAssign<Vec3> for Vec3 {
+    fn sub_assign(&mut self, rhs: Vec3) {
+        *self = internal_sub_vec_vec(self, &rhs);
+    }
+}
+
+impl ops::SubAssign<&Vec3> for Vec3 {
+    fn sub_assign(&mut self, rhs: &Vec3) {
+        *self = internal_sub_vec_vec(self, rhs);
+    }
+}
+
+impl ops::MulAssign<Real> for Vec3 {
+    fn mul_assign(&mut self, rhs: Real) {
+        *self = internal_mul_vec_scalar(self, rhs);
     }
 }
 
@@ -340,6 +534,224 @@ impl ops::Neg for &Vec3 {
     type Output = Vec3;
 
     fn neg(self) -> Self::Output {
-        internal_neg_vec(&self)
+        internal_neg_vec(self)
+    }
+}
+
+// --- INDEX ---
+
+impl ops::Index<usize> for Vec3 {
+    type Output = Real;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            _ => panic!("index {index} is out of range for Vec3"),
+        }
+    }
+}
+
+impl ops::IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            _ => panic!("index {index} is out of range for Vec3"),
+        }
+    }
+}
+
+// --- CONVERSIONS ---
+
+impl From<[Real; 3]> for Vec3 {
+    fn from([x, y, z]: [Real; 3]) -> Self {
+        Vec3::new(x, y, z)
+    }
+}
+
+impl From<(Real, Real, Real)> for Vec3 {
+    fn from((x, y, z): (Real, Real, Real)) -> Self {
+        Vec3::new(x, y, z)
+    }
+}
+
+impl From<Vec3> for [Real; 3] {
+    fn from(v: Vec3) -> Self {
+        [v.x, v.y, v.z]
+    }
+}
+
+impl From<Vec3> for (Real, Real, Real) {
+    fn from(v: Vec3) -> Self {
+        (v.x, v.y, v.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::real::consts;
+
+    // the simd and the scalar build run these with their own arithmetic, both have to give the exact values
+    #[test]
+    fn arithmetic_works_per_component() {
+        let a = Vec3::new(1., -2., 3.);
+        let b = Vec3::new(4., 5., -8.);
+
+        assert!((a + b).approx_eq(&Vec3::new(5., 3., -5.), 0.));
+        assert!((a - b).approx_eq(&Vec3::new(-3., -7., 11.), 0.));
+        assert!((a * b).approx_eq(&Vec3::new(4., -10., -24.), 0.));
+        assert!((a / b).approx_eq(&Vec3::new(0.25, -0.4, -0.375), 0.));
+        assert!((-a).approx_eq(&Vec3::new(-1., 2., -3.), 0.));
+    }
+
+    #[test]
+    fn arithmetic_with_scalars() {
+        let a = Vec3::new(1., -2., 3.);
+
+        assert!((a + 2.).approx_eq(&Vec3::new(3., 0., 5.), 0.));
+        assert!((a - 2.).approx_eq(&Vec3::new(-1., -4., 1.), 0.));
+        assert!((a * 2.).approx_eq(&Vec3::new(2., -4., 6.), 0.));
+        assert!((a / 2.).approx_eq(&Vec3::new(0.5, -1., 1.5), 0.));
+    }
+
+    #[test]
+    fn dot_and_length() {
+        let a = Vec3::new(1., -2., 3.);
+        let b = Vec3::new(4., 5., -8.);
+
+        assert_eq!(Vec3::dot(&a, &b), -30.);
+        assert_eq!(Vec3::new(2., 3., 6.).length(), 7.);
+    }
+
+    #[test]
+    fn reflect_mirrors_at_the_normal() {
+        let reflected = Vec3::reflect(&Vec3::new(1., -1., 0.), &Vec3::new(0., 1., 0.));
+
+        assert!(reflected.approx_eq(&Vec3::new(1., 1., 0.), 0.));
+    }
+
+    #[test]
+    fn refract_passes_straight_on_rays() {
+        let incident = Vec3::new(0., -1., 0.);
+        let normal = Vec3::new(0., 1., 0.);
+
+        assert!(Vec3::refract(&incident, &normal, 1.).unwrap().approx_eq(&incident, 1e-6));
+        // a straight on ray isn't bent by glass either
+        assert!(Vec3::refract(&incident, &normal, 1. / 1.5).unwrap().approx_eq(&incident, 1e-6));
+    }
+
+    #[test]
+    fn refract_bends_by_snells_law() {
+        // 45 degrees from air into glass
+        let incident = Vec3::new(1., -1., 0.).normalize();
+        let refracted = Vec3::refract(&incident, &Vec3::new(0., 1., 0.), 1. / 1.5).unwrap();
+
+        assert!((refracted.length() - 1.).abs() < 1e-6);
+        assert!((refracted.x - consts::FRAC_1_SQRT_2 / 1.5).abs() < 1e-6);
+        assert!(refracted.y < 0.);
+    }
+
+    #[test]
+    fn refract_at_a_grazing_angle_is_total_internal_reflection() {
+        // leaving glass at 80 degrees from the normal, past the critical angle of about 42 degrees
+        let angle = Real::to_radians(80.);
+        let incident = Vec3::new(angle.sin(), angle.cos(), 0.);
+
+        assert!(Vec3::refract(&incident, &Vec3::new(0., -1., 0.), 1.5).is_none());
+    }
+
+    #[test]
+    fn lerp_goes_from_a_to_b() {
+        let a = Vec3::new(1., -2., 4.);
+        let b = Vec3::new(3., 6., -8.);
+
+        assert!(Vec3::lerp(&a, &b, 0.).approx_eq(&a, 0.));
+        assert!(Vec3::lerp(&a, &b, 1.).approx_eq(&b, 0.));
+        assert!(Vec3::lerp(&a, &b, 0.5).approx_eq(&((a + b) / 2.), 0.));
+    }
+
+    #[test]
+    fn rotate_turns_x_into_y_about_z() {
+        let rotated = Vec3::rotate(&Vec3::new(1., 0., 0.), &Vec3::new(0., 0., 1.), consts::FRAC_PI_2);
+
+        assert!(rotated.approx_eq(&Vec3::new(0., 1., 0.), 1e-6));
+    }
+
+    #[test]
+    fn rotate_normalizes_the_axis() {
+        let v = Vec3::new(1., 2., 3.);
+        let rotated = Vec3::rotate(&v, &Vec3::new(0., 5., 0.), 0.7);
+
+        assert!(rotated.approx_eq(&v.rotate_y(0.7), 1e-6));
+        // the component along the axis stays, the length is preserved
+        assert!((rotated.y - 2.).abs() < 1e-6);
+        assert!((rotated.length() - v.length()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn assign_operators_match_the_binary_ones() {
+        let a = Vec3::new(1., -2., 3.);
+        let b = Vec3::new(4., 5., -8.);
+
+        let mut c = a;
+        c += b;
+        assert!(c.approx_eq(&(a + b), 0.));
+        c -= &b;
+        assert!(c.approx_eq(&a, 0.));
+        c += &b;
+        c -= b;
+        assert!(c.approx_eq(&a, 0.));
+        c *= 2.5;
+        assert!(c.approx_eq(&(a * 2.5), 0.));
+    }
+
+    #[test]
+    fn index_reads_and_writes_the_components() {
+        let mut v = Vec3::new(1., 2., 3.);
+
+        assert_eq!([v[0], v[1], v[2]], [1., 2., 3.]);
+
+        for i in 0..3 {
+            v[i] *= 10.;
+        }
+        assert!(v.approx_eq(&Vec3::new(10., 20., 30.), 0.));
+    }
+
+    #[test]
+    #[should_panic(expected = "index 3 is out of range for Vec3")]
+    fn index_out_of_range_panics() {
+        let _ = Vec3::zero()[3];
+    }
+
+    #[test]
+    #[should_panic(expected = "index 3 is out of range for Vec3")]
+    fn index_mut_out_of_range_panics() {
+        Vec3::zero()[3] = 1.;
+    }
+
+    #[test]
+    fn converts_to_and_from_arrays_and_tuples() {
+        let v = Vec3::from([1., 2., 3.]);
+        assert!(v.approx_eq(&Vec3::new(1., 2., 3.), 0.));
+        assert_eq!(<[Real; 3]>::from(v), [1., 2., 3.]);
+
+        let v: Vec3 = (4., 5., 6.).into();
+        assert!(v.approx_eq(&Vec3::new(4., 5., 6.), 0.));
+        let tuple: (Real, Real, Real) = v.into();
+        assert_eq!(tuple, (4., 5., 6.));
+    }
+
+    #[test]
+    fn approx_eq_compares_every_component() {
+        let v = Vec3::new(1., 2., 3.);
+
+        assert!(v.approx_eq(&Vec3::new(1.05, 1.95, 3.), 0.1));
+        assert!(!v.approx_eq(&Vec3::new(1.2, 2., 3.), 0.1));
+        assert!(!v.approx_eq(&Vec3::new(1., 2.2, 3.), 0.1));
+        assert!(!v.approx_eq(&Vec3::new(1., 2., 2.8), 0.1));
     }
 }
\ No newline at end of file
@@ -1,17 +1,19 @@
 use std::ops;
 use num::clamp;
+use serde::{Deserialize, Serialize};
+use crate::real::Real;
 use crate::vec3::Vec3;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub struct Vec4 {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-    pub w: f64,
+    pub x: Real,
+    pub y: Real,
+    pub z: Real,
+    pub w: Real,
 }
 
 impl Vec4 {
-    pub const fn new(x: f64, y: f64, z: f64, w: f64) -> Self {
+    pub const fn new(x: Real, y: Real, z: Real, w: Real) -> Self {
         Vec4 {
             x,
             y,
@@ -20,11 +22,11 @@ impl Vec4 {
         }
     }
 
-    pub fn from_vec3(vec3: &Vec3, w: f64) -> Self {
+    pub fn from_vec3(vec3: &Vec3, w: Real) -> Self {
         Vec4 {
-            x: vec3.x.clone(),
-            y: vec3.y.clone(),
-            z: vec3.z.clone(),
+            x: vec3.x,
+            y: vec3.y,
+            z: vec3.z,
             w,
         }
     }
@@ -37,15 +39,15 @@ impl Vec4 {
         Vec4::new(0., 0., 0., 0.)
     }
 
-    pub fn dot(a: &Self, b: &Self) -> f64 {
+    pub fn dot(a: &Self, b: &Self) -> Real {
         a.x * b.x + a.y * b.y + a.z * b.z + a.w * b.w
     }
 
-    pub fn sqr_length(&self) -> f64 {
+    pub fn sqr_length(&self) -> Real {
         Vec4::dot(self, self)
     }
 
-    pub fn length(&self) -> f64 {
+    pub fn length(&self) -> Real {
         self.sqr_length().sqrt()
     }
 
@@ -62,7 +64,7 @@ impl Vec4 {
         }
     }
 
-    pub fn min(a: &Self, b: f64) -> Self {
+    pub fn min(a: &Self, b: Real) -> Self {
         Vec4 {
             x: a.x.min(b),
             y: a.y.min(b),
@@ -71,7 +73,7 @@ impl Vec4 {
         }
     }
 
-    pub fn max(a: &Self, b: f64) -> Self {
+    pub fn max(a: &Self, b: Real) -> Self {
         Vec4 {
             x: a.x.max(b),
             y: a.y.max(b),
@@ -80,15 +82,15 @@ impl Vec4 {
         }
     }
 
-    pub fn max_element(&self) -> f64 {
-        return self.x.max(self.y).max(self.z).max(self.w);
+    pub fn max_element(&self) -> Real {
+        self.x.max(self.y).max(self.z).max(self.w)
     }
 
-    pub fn min_element(&self) -> f64 {
-        return self.x.min(self.y).min(self.z).max(self.w);
+    pub fn min_element(&self) -> Real {
+        self.x.min(self.y).min(self.z).min(self.w)
     }
 
-    pub fn clamp(&self, min: f64, max: f64) -> Self {
+    pub fn clamp(&self, min: Real, max: Real) -> Self {
         Vec4 {
             x: clamp(self.x, min, max),
             y: clamp(self.y, min, max),
@@ -97,63 +99,63 @@ impl Vec4 {
         }
     }
 
-    pub fn q_square(&self) -> Self {
-        Vec4 {
-            x: self.x * self.x - self.y * self.y - self.z * self.z - self.w * self.w,
-            y: self.x * self.y * 2.,
-            z: self.x * self.z * 2.,
-            w: self.x * self.w * 2.,
-        }
+    pub fn lerp(a: &Self, b: &Self, t: Real) -> Vec4 {
+        a + (b - a) * t
     }
 
-    pub fn q_cube(&self) -> Self {
-        let sqr_self = self * self;
-        let new_x_factor = sqr_self.x - sqr_self.y * 3. - sqr_self.z * 3. - sqr_self.w * 3.;
-        let new_other_factor = sqr_self.x * 3. - sqr_self.y - sqr_self.z - sqr_self.w;
+    pub fn to_pixel_data(self, gamma: Real) -> [u8; 3] {
+        let c = self.clamp(0., 1.);
+        let inv_gamma = 1. / gamma;
 
-        Vec4 {
-            x: self.x * new_x_factor,
-            y: self.y * new_other_factor,
-            z: self.z * new_other_factor,
-            w: self.w * new_other_factor,
-        }
+        [(255.999 * c.x.powf(inv_gamma)) as u8, (255.999 * c.y.powf(inv_gamma)) as u8, (255.999 * c.z.powf(inv_gamma)) as u8]
+    }
+
+    // like to_pixel_data with w as the alpha channel, which is coverage and not gamma corrected
+    pub fn to_pixel_data_alpha(self, gamma: Real) -> [u8; 4] {
+        let [r, g, b] = self.to_pixel_data(gamma);
+
+        [r, g, b, (255.999 * self.w.clamp(0., 1.)) as u8]
     }
 
-    pub fn to_pixel_data(self) -> [u8; 3] {
-        [(255.999 * self.x) as u8, (255.999 * self.y) as u8, (255.999 * self.z) as u8]
+    // component wise comparison with a tolerance, meant for tests and tolerance sensitive code, not for hashing
+    pub fn approx_eq(&self, other: &Self, eps: Real) -> bool {
+        (self.x - other.x).abs() <= eps
+            && (self.y - other.y).abs() <= eps
+            && (self.z - other.z).abs() <= eps
+            && (self.w - other.w).abs() <= eps
     }
 }
 
 
 fn internal_add_vec_vec(a: &Vec4, b: &Vec4) -> Vec4 {
-    Vec4::new(a.x + b.x, a.y + b.y, a.z + b.z, a.w + b.z)
+    Vec4::new(a.x + b.x, a.y + b.y, a.z + b.z, a.w + b.w)
 }
 
-fn internal_add_vec_scalar(a: &Vec4, b: f64) -> Vec4 {
+fn internal_add_vec_scalar(a: &Vec4, b: Real) -> Vec4 {
     Vec4::new(a.x + b, a.y + b, a.z + b, a.w + b)
 }
 
 fn internal_sub_vec_vec(a: &Vec4, b: &Vec4) -> Vec4 {
-    Vec4::new(a.x - b.x, a.y - b.y, a.z - b.z, a.w - b.z)
+    Vec4::new(a.x - b.x, a.y - b.y, a.z - b.z, a.w - b.w)
 }
 
-fn internal_sub_vec_scalar(a: &Vec4, b: f64) -> Vec4 {
+fn internal_sub_vec_scalar(a: &Vec4, b: Real) -> Vec4 {
     Vec4::new(a.x - b, a.y - b, a.z - b, a.w - b)
 }
 
 fn internal_mul_vec_vec(a: &Vec4, b: &Vec4) -> Vec4 {
-    Vec4::new(a.x * b.x, a.y * b.y, a.z * b.z, a.w * b.z)
+    Vec4::new(a.x * b.x, a.y * b.y, a.z * b.z, a.w * b.w)
 }
 
-fn internal_mul_vec_scalar(a: &Vec4, b: f64) -> Vec4 {
+fn internal_mul_vec_scalar(a: &Vec4, b: Real) -> Vec4 {
     Vec4::new(a.x * b, a.y * b, a.z * b, a.w * b)
 }
 
 fn internal_div_vec_vec(a: &Vec4, b: &Vec4) -> Vec4 {
-    Vec4::new(a.x / b.x, a.y / b.y, a.z / b.z, a.w / b.z)
+    Vec4::new(a.x / b.x, a.y / b.y, a.z / b.z, a.w / b.w)
 }
 
-fn internal_div_vec_scalar(a: &Vec4, b: f64) -> Vec4 {
+fn internal_div_vec_scalar(a: &Vec4, b: Real) -> Vec4 {
     Vec4::new(a.x / b, a.y / b, a.z / b, a.w / b)
 }
 
@@ -175,7 +177,7 @@ impl ops::Add<Vec4> for &Vec4 {
     type Output = Vec4;
 
     fn add(self, rhs: Vec4) -> Self::Output {
-        internal_add_vec_vec(&self, &rhs)
+        internal_add_vec_vec(self, &rhs)
     }
 }
 
@@ -183,7 +185,7 @@ impl ops::Add<&Vec4> for Vec4 {
     type Output = Vec4;
 
     fn add(self, rhs: &Vec4) -> Self::Output {
-        internal_add_vec_vec(&self, &rhs)
+        internal_add_vec_vec(&self, rhs)
     }
 }
 
@@ -191,23 +193,23 @@ impl ops::Add<&Vec4> for &Vec4 {
     type Output = Vec4;
 
     fn add(self, rhs: &Vec4) -> Self::Output {
-        internal_add_vec_vec(&self, &rhs)
+        internal_add_vec_vec(self, rhs)
     }
 }
 
-impl ops::Add<f64> for Vec4 {
+impl ops::Add<Real> for Vec4 {
     type Output = Vec4;
 
-    fn add(self, rhs: f64) -> Self::Output {
+    fn add(self, rhs: Real) -> Self::Output {
         internal_add_vec_scalar(&self, rhs)
     }
 }
 
-impl ops::Add<f64> for &Vec4 {
+impl ops::Add<Real> for &Vec4 {
     type Output = Vec4;
 
-    fn add(self, rhs: f64) -> Self::Output {
-        internal_add_vec_scalar(&self, rhs)
+    fn add(self, rhs: Real) -> Self::Output {
+        internal_add_vec_scalar(self, rhs)
     }
 }
 
@@ -225,7 +227,7 @@ impl ops::Sub<Vec4> for &Vec4 {
     type Output = Vec4;
 
     fn sub(self, rhs: Vec4) -> Self::Output {
-        internal_sub_vec_vec(&self, &rhs)
+        internal_sub_vec_vec(self, &rhs)
     }
 }
 
@@ -233,7 +235,7 @@ impl ops::Sub<&Vec4> for Vec4 {
     type Output = Vec4;
 
     fn sub(self, rhs: &Vec4) -> Self::Output {
-        internal_sub_vec_vec(&self, &rhs)
+        internal_sub_vec_vec(&self, rhs)
     }
 }
 
@@ -241,23 +243,23 @@ impl ops::Sub<&Vec4> for &Vec4 {
     type Output = Vec4;
 
     fn sub(self, rhs: &Vec4) -> Self::Output {
-        internal_sub_vec_vec(&self, &rhs)
+        internal_sub_vec_vec(self, rhs)
     }
 }
 
-impl ops::Sub<f64> for Vec4 {
+impl ops::Sub<Real> for Vec4 {
     type Output = Vec4;
 
-    fn sub(self, rhs: f64) -> Self::Output {
+    fn sub(self, rhs: Real) -> Self::Output {
         internal_sub_vec_scalar(&self, rhs)
     }
 }
 
-impl ops::Sub<f64> for &Vec4 {
+impl ops::Sub<Real> for &Vec4 {
     type Output = Vec4;
 
-    fn sub(self, rhs: f64) -> Self::Output {
-        internal_sub_vec_scalar(&self, rhs)
+    fn sub(self, rhs: Real) -> Self::Output {
+        internal_sub_vec_scalar(self, rhs)
     }
 }
 
@@ -275,37 +277,53 @@ impl ops::Mul<Vec4> for &Vec4 {
     type Output = Vec4;
 
     fn mul(self, rhs: Vec4) -> Self::Output {
-        internal_mul_vec_vec(&self, &rhs)
+        internal_mul_vec_vec(self, &rhs)
     }
 }
 impl ops::Mul<&Vec4> for Vec4 {
     type Output = Vec4;
 
     fn mul(self, rhs: &Vec4) -> Self::Output {
-        internal_mul_vec_vec(&self, &rhs)
+        internal_mul_vec_vec(&self, rhs)
     }
 }
 impl ops::Mul<&Vec4> for &Vec4 {
     type Output = Vec4;
 
     fn mul(self, rhs: &Vec4) -> Self::Output {
-        internal_mul_vec_vec(&self, &rhs)
+        internal_mul_vec_vec(self, rhs)
     }
 }
 
-impl ops::Mul<f64> for Vec4 {
+impl ops::Mul<Real> for Vec4 {
     type Output = Vec4;
 
-    fn mul(self, rhs: f64) -> Self::Output {
+    fn mul(self, rhs: Real) -> Self::Output {
         internal_mul_vec_scalar(&self, rhs)
     }
 }
 
-impl ops::Mul<f64> for &Vec4 {
+impl ops::Mul<Real> for &Vec4 {
     type Output = Vec4;
 
-    fn mul(self, rhs: f64) -> Self::Output {
-        internal_mul_vec_scalar(&self, rhs)
+    fn mul(self, rhs: Real) -> Self::Output {
+        internal_mul_vec_scalar(self, rhs)
+    }
+}
+
+impl ops::Mul<Vec4> for Real {
+    type Output = Vec4;
+
+    fn mul(self, rhs: Vec4) -> Self::Output {
+        internal_mul_vec_scalar(&rhs, self)
+    }
+}
+
+impl ops::Mul<&Vec4> for Real {
+    type Output = Vec4;
+
+    fn mul(self, rhs: &Vec4) -> Self::Output {
+        internal_mul_vec_scalar(rhs, self)
     }
 }
 
@@ -323,7 +341,7 @@ impl ops::Div<Vec4> for &Vec4 {
     type Output = Vec4;
 
     fn div(self, rhs: Vec4) -> Self::Output {
-        internal_div_vec_vec(&self, &rhs)
+        internal_div_vec_vec(self, &rhs)
     }
 }
 
@@ -331,7 +349,7 @@ impl ops::Div<&Vec4> for Vec4 {
     type Output = Vec4;
 
     fn div(self, rhs: &Vec4) -> Self::Output {
-        internal_div_vec_vec(&self, &rhs)
+        internal_div_vec_vec(&self, rhs)
     }
 }
 
@@ -339,23 +357,55 @@ impl ops::Div<&Vec4> for &Vec4 {
     type Output = Vec4;
 
     fn div(self, rhs: &Vec4) -> Self::Output {
-        internal_div_vec_vec(&self, &rhs)
+        internal_div_vec_vec(self, rhs)
     }
 }
 
-impl ops::Div<f64> for Vec4 {
+impl ops::Div<Real> for Vec4 {
     type Output = Vec4;
 
-    fn div(self, rhs: f64) -> Self::Output {
+    fn div(self, rhs: Real) -> Self::Output {
         internal_div_vec_scalar(&self, rhs)
     }
 }
 
-impl ops::Div<f64> for &Vec4 {
+impl ops::Div<Real> for &Vec4 {
     type Output = Vec4;
 
-    fn div(self, rhs: f64) -> Self::Output {
-        internal_div_vec_scalar(&self, rhs)
+    fn div(self, rhs: Real) -> Self::Output {
+        internal_div_vec_scalar(self, rhs)
+    }
+}
+
+// --- ASSIGN ---
+
+impl ops::AddAssign<Vec4> for Vec4 {
+    fn add_assign(&mut self, rhs: Vec4) {
+        *self = internal_add_vec_vec(self, &rhs);
+    }
+}
+
+impl ops::AddAssign<&Vec4> for Vec4 {
+    fn add_assign(&mut self, rhs: &Vec4) {
+        *self = internal_add_vec_vec(self, rhs);
+    }
+}
+
+impl ops::SubAssign<Vec4> for Vec4 {
+    fn sub_assign(&mut self, rhs: Vec4) {
+        *self = internal_sub_vec_vec(self, &rhs);
+    }
+}
+
+impl ops::SubAssign<&Vec4> for Vec4 {
+    fn sub_assign(&mut self, rhs: &Vec4) {
+        *self = internal_sub_vec_vec(self, rhs);
+    }
+}
+
+impl ops::MulAssign<Real> for Vec4 {
+    fn mul_assign(&mut self, rhs: Real) {
+        *self = internal_mul_vec_scalar(self, rhs);
     }
 }
 
@@ -373,6 +423,175 @@ impl ops::Neg for &Vec4 {
     type Output = Vec4;
 
     fn neg(self) -> Self::Output {
-        internal_neg_vec(&self)
+        internal_neg_vec(self)
+    }
+}
+
+// --- INDEX ---
+
+impl ops::Index<usize> for Vec4 {
+    type Output = Real;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            3 => &self.w,
+            _ => panic!("index {index} is out of range for Vec4"),
+        }
+    }
+}
+
+impl ops::IndexMut<usize> for Vec4 {
+    fn index_mut(&mut self, index: usize) -> &mut Self::Output {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            3 => &mut self.w,
+            _ => panic!("index {index} is out of range for Vec4"),
+        }
+    }
+}
+
+// --- CONVERSIONS ---
+
+impl From<[Real; 4]> for Vec4 {
+    fn from([x, y, z, w]: [Real; 4]) -> Self {
+        Vec4::new(x, y, z, w)
+    }
+}
+
+impl From<(Real, Real, Real, Real)> for Vec4 {
+    fn from((x, y, z, w): (Real, Real, Real, Real)) -> Self {
+        Vec4::new(x, y, z, w)
+    }
+}
+
+impl From<Vec4> for [Real; 4] {
+    fn from(v: Vec4) -> Self {
+        [v.x, v.y, v.z, v.w]
+    }
+}
+
+impl From<Vec4> for (Real, Real, Real, Real) {
+    fn from(v: Vec4) -> Self {
+        (v.x, v.y, v.z, v.w)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_uses_the_w_of_both_vectors() {
+        assert_eq!((Vec4::new(1., 2., 3., 4.) + Vec4::new(10., 20., 30., 40.)).w, 44.);
+    }
+
+    #[test]
+    fn sub_uses_the_w_of_both_vectors() {
+        assert_eq!((Vec4::new(10., 20., 30., 40.) - Vec4::new(1., 2., 3., 4.)).w, 36.);
+    }
+
+    #[test]
+    fn mul_uses_the_w_of_both_vectors() {
+        assert_eq!((Vec4::new(1., 2., 3., 4.) * Vec4::new(10., 20., 30., 40.)).w, 160.);
+    }
+
+    #[test]
+    fn div_uses_the_w_of_both_vectors() {
+        assert_eq!((Vec4::new(10., 20., 30., 40.) / Vec4::new(1., 2., 3., 4.)).w, 10.);
+    }
+
+    #[test]
+    fn div_by_a_scalar_divides_w() {
+        assert_eq!((Vec4::new(2., 4., 6., 8.) / 2.).w, 4.);
+    }
+
+    #[test]
+    fn min_element_includes_w() {
+        assert_eq!(Vec4::new(3., 2., 1., 0.).min_element(), 0.);
+        assert_eq!(Vec4::new(0., 1., 2., 3.).max_element(), 3.);
+    }
+
+    #[test]
+    fn to_pixel_data_with_gamma_1_is_the_raw_mapping() {
+        for c in [Vec4::new(0., 0.25, 0.5, 1.), Vec4::new(0.1, 0.7, 0.999, 0.), Vec4::new(1., 0.003, 0.9, 0.5)] {
+            let raw = [(255.999 * c.x) as u8, (255.999 * c.y) as u8, (255.999 * c.z) as u8];
+            assert_eq!(c.to_pixel_data(1.), raw);
+        }
+    }
+
+    #[test]
+    fn to_pixel_data_applies_the_gamma() {
+        assert_eq!(Vec4::new(0.25, 0., 1., 1.).to_pixel_data(2.), [127, 0, 255]);
+        // out of range colors are clamped
+        assert_eq!(Vec4::new(-1., 2., 0.5, 1.).to_pixel_data(1.), [0, 255, 127]);
+    }
+
+    #[test]
+    fn assign_operators_match_the_binary_ones() {
+        let a = Vec4::new(1., -2., 3., 0.5);
+        let b = Vec4::new(4., 5., -8., 2.);
+
+        let mut c = a;
+        c += b;
+        assert!(c.approx_eq(&(a + b), 0.));
+        c -= &b;
+        assert!(c.approx_eq(&a, 0.));
+        c += &b;
+        c -= b;
+        assert!(c.approx_eq(&a, 0.));
+        c *= 2.5;
+        assert!(c.approx_eq(&(a * 2.5), 0.));
+    }
+
+    #[test]
+    fn index_reads_and_writes_the_components() {
+        let mut v = Vec4::new(1., 2., 3., 4.);
+
+        assert_eq!([v[0], v[1], v[2], v[3]], [1., 2., 3., 4.]);
+
+        for i in 0..4 {
+            v[i] *= 10.;
+        }
+        assert!(v.approx_eq(&Vec4::new(10., 20., 30., 40.), 0.));
+    }
+
+    #[test]
+    #[should_panic(expected = "index 4 is out of range for Vec4")]
+    fn index_out_of_range_panics() {
+        let _ = Vec4::zero()[4];
+    }
+
+    #[test]
+    #[should_panic(expected = "index 4 is out of range for Vec4")]
+    fn index_mut_out_of_range_panics() {
+        Vec4::zero()[4] = 1.;
+    }
+
+    #[test]
+    fn converts_to_and_from_arrays_and_tuples() {
+        let v = Vec4::from([1., 2., 3., 4.]);
+        assert!(v.approx_eq(&Vec4::new(1., 2., 3., 4.), 0.));
+        assert_eq!(<[Real; 4]>::from(v), [1., 2., 3., 4.]);
+
+        let v: Vec4 = (5., 6., 7., 8.).into();
+        assert!(v.approx_eq(&Vec4::new(5., 6., 7., 8.), 0.));
+        let tuple: (Real, Real, Real, Real) = v.into();
+        assert_eq!(tuple, (5., 6., 7., 8.));
+    }
+
+    #[test]
+    fn approx_eq_compares_every_component() {
+        let v = Vec4::new(1., 2., 3., 4.);
+
+        assert!(v.approx_eq(&Vec4::new(1.05, 1.95, 3., 4.05), 0.1));
+        assert!(!v.approx_eq(&Vec4::new(1.2, 2., 3., 4.), 0.1));
+        assert!(!v.approx_eq(&Vec4::new(1., 2.2, 3., 4.), 0.1));
+        assert!(!v.approx_eq(&Vec4::new(1., 2., 2.8, 4.), 0.1));
+        assert!(!v.approx_eq(&Vec4::new(1., 2., 3., 4.2), 0.1));
     }
 }
\ No newline at end of file
@@ -0,0 +1,143 @@
+use crate::real::Real;
+use crate::vec3::Vec3;
+
+// the seed of noise3 and fbm, the pattern is the same on every run
+const DEFAULT_SEED: u32 = 0;
+
+// improved perlin noise https://mrl.nyu.edu/~perlin/noise in [-1, 1], with the gradients picked by
+// hashing the lattice coordinates instead of a permutation table
+pub fn noise3(p: &Vec3) -> Real {
+    noise3_seeded(p, DEFAULT_SEED)
+}
+
+pub fn noise3_seeded(p: &Vec3, seed: u32) -> Real {
+    let (x0, y0, z0) = (p.x.floor(), p.y.floor(), p.z.floor());
+    let (fx, fy, fz) = (p.x - x0, p.y - y0, p.z - z0);
+    let (ix, iy, iz) = (x0 as i32, y0 as i32, z0 as i32);
+
+    let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+
+    // contribution of the gradient of a corner at offset (dx, dy, dz) from the lower corner
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        let h = hash(ix.wrapping_add(dx), iy.wrapping_add(dy), iz.wrapping_add(dz), seed);
+        gradient(h, fx - dx as Real, fy - dy as Real, fz - dz as Real)
+    };
+
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), u);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), u);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), u);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), u);
+
+    let y0 = lerp(x00, x10, v);
+    let y1 = lerp(x01, x11, v);
+
+    // the extremes of perlin noise reach slightly past 1
+    lerp(y0, y1, w).clamp(-1., 1.)
+}
+
+// fractal brownian motion, octaves of noise where each one has lacunarity times the frequency and gain
+// times the amplitude of the previous one. normalized to [-1, 1]
+pub fn fbm(p: &Vec3, octaves: u32, lacunarity: Real, gain: Real) -> Real {
+    let mut sum = 0.;
+    let mut amplitude = 1.;
+    let mut total_amplitude = 0.;
+    let mut q = *p;
+
+    for octave in 0..octaves {
+        // a different seed per octave keeps the octaves from lining up at the origin
+        sum += amplitude * noise3_seeded(&q, DEFAULT_SEED.wrapping_add(octave));
+        total_amplitude += amplitude;
+
+        q *= lacunarity;
+        amplitude *= gain;
+    }
+
+    if total_amplitude > 0. {
+        sum / total_amplitude
+    } else {
+        0.
+    }
+}
+
+// smootherstep, its first and second derivative are 0 at the lattice points
+fn fade(t: Real) -> Real {
+    t * t * t * (t * (t * 6. - 15.) + 10.)
+}
+
+fn lerp(a: Real, b: Real, t: Real) -> Real {
+    a + (b - a) * t
+}
+
+// the 12 edge directions of a cube, with 4 of them repeated to pick them with the low 4 bits
+fn gradient(hash: u32, x: Real, y: Real, z: Real) -> Real {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}
+
+fn hash(x: i32, y: i32, z: i32, seed: u32) -> u32 {
+    let mut h = seed
+        ^ (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f);
+
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^ (h >> 15)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::Rng;
+
+    fn sample_points() -> Vec<Vec3> {
+        let mut rng = Rng::new(98);
+        (0..2000).map(|_| Vec3::new(rng.range(-50., 50.), rng.range(-50., 50.), rng.range(-50., 50.))).collect()
+    }
+
+    #[test]
+    fn noise_and_fbm_stay_in_range() {
+        for p in sample_points() {
+            assert!((-1. ..=1.).contains(&noise3(&p)), "at {p:?}");
+            assert!((-1. ..=1.).contains(&fbm(&p, 5, 2., 0.5)), "at {p:?}");
+        }
+    }
+
+    #[test]
+    fn noise_is_0_on_the_lattice() {
+        for p in [Vec3::zero(), Vec3::new(1., 2., 3.), Vec3::new(-7., 4., -12.)] {
+            assert_eq!(noise3(&p), 0.);
+        }
+    }
+
+    #[test]
+    fn noise_is_continuous() {
+        let delta = 1e-4;
+
+        for p in sample_points() {
+            let d = noise3(&p);
+
+            for offset in [Vec3::new(delta, 0., 0.), Vec3::new(0., delta, 0.), Vec3::new(0., 0., delta)] {
+                // the gradients are at most sqrt(2) long, so the noise can't change much faster than that
+                assert!((noise3(&(p + offset)) - d).abs() < 10. * delta, "at {p:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn noise_is_deterministic_per_seed() {
+        let p = Vec3::new(0.3, 1.7, -2.2);
+
+        assert_eq!(noise3_seeded(&p, 1), noise3_seeded(&p, 1));
+        assert_ne!(noise3_seeded(&p, 1), noise3_seeded(&p, 2));
+    }
+}
@@ -1,19 +1,118 @@
 use crate::distance_fields::{DistanceField, DistanceFunction};
+use crate::light::Light;
+use crate::material::Material;
 use crate::ray::Ray;
+use crate::real::Real;
+use crate::rng::Rng;
 use crate::vec3::Vec3;
 use crate::vec4::Vec4;
 
+#[derive(Debug, Clone, Copy)]
+pub enum ToneMap {
+    None,
+    Reinhard,
+    Aces,
+}
+
+impl ToneMap {
+    pub fn apply(&self, c: Real) -> Real {
+        match self {
+            ToneMap::None => c,
+            ToneMap::Reinhard => c / (c + 1.),
+            // fitted curve https://knarkowicz.wordpress.com/2016/01/06/aces-filmic-tone-mapping-curve/
+            ToneMap::Aces => ((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0., 1.),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum NormalMode {
+    // six samples, central differences along each axis
+    CentralDifferences,
+    // four samples on the corners of a tetrahedron
+    Tetrahedron,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum ShadowMode {
+    // everything is fully lit
+    None,
+    // crisp shadows, any occluder blocks the light completely
+    Hard,
+    // penumbra estimate controlled by shadow_fuzziness
+    Soft,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Background {
+    Solid(Vec3),
+    // vertical gradient from the bottom to the top color depending on the ray direction
+    Gradient {
+        bottom: Vec3,
+        top: Vec3,
+    },
+}
+
+impl Background {
+    pub fn sample(&self, dir: &Vec3) -> Vec3 {
+        match self {
+            Background::Solid(color) => *color,
+            Background::Gradient { bottom, top } => {
+                let t = 0.5 * (dir.y + 1.);
+                Vec3::lerp(bottom, top, t)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Ambient {
+    // the same bg_light_color from every direction
+    Flat,
+    // blend from the ground color for normals facing down to the sky color for normals facing up
+    Hemisphere {
+        sky: Vec3,
+        ground: Vec3,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RayStats {
+    pub iterations: i32,
+    pub hit: bool,
+    pub final_t: Real,
+}
+
+impl RayStats {
+    // distance to the hit surface along the ray, infinity if nothing was hit
+    pub fn depth(&self) -> Real {
+        if self.hit {
+            self.final_t
+        } else {
+            Real::INFINITY
+        }
+    }
+}
+
 pub struct RayMarcher {
     // quality
     pub max_iterations: i32,
-    pub max_distance: f64,
-    pub accuracy: f64,
+    pub max_distance: Real,
+    pub accuracy: Real,
+    // grows the hit threshold with the distance by the angular size of a pixel,
+    // so far away surfaces are hit once they are smaller than a pixel
+    pub cone_tracing: bool,
+    pub pixel_angle: Real,
 
     // misc
-    pub debug: bool,
+    pub gamma: Real,
+    pub tone_map: ToneMap,
+    // linear multiplier on the final color before tone mapping
+    pub exposure: Real,
 
     // normals
-    pub normal_accuracy: f64,
+    pub normal_mode: NormalMode,
+    pub normal_accuracy: Real,
     pub offset_x: Vec3,
     pub offset_y: Vec3,
     pub offset_z: Vec3,
@@ -21,161 +120,819 @@ pub struct RayMarcher {
     // scene
     pub scene: DistanceField,
     pub obj_color: Vec3,
+    pub background: Background,
 
-    // sun light
-    pub light_dir: Vec3,
-    pub light_color: Vec3,
-    pub light_intensity: f64,
+    // specular
+    pub shininess: Real,
+    pub specular_intensity: Real,
+
+    // reflections
+    pub reflectivity: Real,
+    pub max_bounces: i32,
+    // base reflectance at normal incidence, when set the reflectivity scales the fresnel term, which rises
+    // from f0 towards 1 at grazing angles
+    pub f0: Option<Real>,
+
+    // lights
+    pub lights: Vec<Light>,
 
     // indirect light
     pub bg_light_color: Vec3,
-    pub bg_light_intensity: f64,
+    pub bg_light_intensity: Real,
+    pub ambient: Ambient,
+    // cosine weighted rays per hit that gather the direct light of the surfaces around it instead of the
+    // ambient term, 0 disables the bounce
+    pub gi_samples: u32,
+
+    // fog
+    pub fog_color: Vec3,
+    pub fog_density: Real,
 
     // shadow
-    pub shadow_dist_min: f64,
-    pub shadow_dist_max: f64,
-    pub shadow_fuzziness: f64,
+    // the shadow ray starts shadow_bias away from the surface along the normal and skips the first
+    // shadow_dist_min along the light direction, both keep surfaces from shadowing themselves (acne)
+    pub shadow_mode: ShadowMode,
+    pub shadow_bias: Real,
+    pub shadow_dist_min: Real,
+    pub shadow_dist_max: Real,
+    pub shadow_fuzziness: Real,
+    pub shadow_max_steps: i32,
 
     // AO
-    pub ao_step_size: f64,
-    pub ao_intensity: f64,
+    pub ao_step_size: Real,
+    pub ao_intensity: Real,
     pub ao_iterations: i32,
+    // exponent applied to the final AO term, values above 1 darken the creases more
+    pub ao_falloff: Real,
 }
 
+// schlick's approximation of the fresnel reflectance https://en.wikipedia.org/wiki/Schlick%27s_approximation
+pub fn schlick(cos_theta: Real, f0: Real) -> Real {
+    f0 + (1. - f0) * (1. - cos_theta).powi(5)
+}
+
+// the render threads share one ray marcher
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<RayMarcher>();
+};
+
 pub fn create_ray_marcher(scene: DistanceField) -> RayMarcher {
-    let max_iterations = 4000;
-    let max_distance = 7.;
-    let accuracy = 0.00001;
+    RayMarcherBuilder::new(scene).build()
+}
+
+// every setting that isn't set explicitly falls back to its default in build
+pub struct RayMarcherBuilder {
+    scene: DistanceField,
 
-    let debug = false;
+    // quality
+    max_iterations: Option<i32>,
+    max_distance: Option<Real>,
+    accuracy: Option<Real>,
+    cone_tracing: Option<bool>,
+    pixel_angle: Option<Real>,
+
+    // misc
+    gamma: Option<Real>,
+    tone_map: Option<ToneMap>,
+    exposure: Option<Real>,
 
-    let obj_color = Vec3::new(1., 1., 1.);
+    // scene
+    obj_color: Option<Vec3>,
+    background: Option<Background>,
 
-    let normal_accuracy = 0.000001;
-    let offset_x = Vec3::new(normal_accuracy, 0., 0.);
-    let offset_y = Vec3::new(0., normal_accuracy, 0.);
-    let offset_z = Vec3::new(0., 0., normal_accuracy);
+    // specular
+    shininess: Option<Real>,
+    specular_intensity: Option<Real>,
 
-    let light_dir = Vec3::new(0.5, -1., 0.5).normalize();
-    let light_color = Vec3::new(1., 1., 1.);
-    let light_intensity = 1.;
+    // reflections
+    reflectivity: Option<Real>,
+    max_bounces: Option<i32>,
+    f0: Option<Real>,
 
-    let bg_light_color = Vec3::new(1., 1., 1.);
-    let bg_light_intensity = 0.1;
+    // normals
+    normal_mode: Option<NormalMode>,
+    normal_accuracy: Option<Real>,
 
-    let shadow_dist_min = 0.0;
-    let shadow_dist_max = max_distance;
-    let shadow_fuzziness = 5.;
+    // lights
+    lights: Option<Vec<Light>>,
 
-    let ao_step_size = 0.05;
-    let ao_intensity = 0.3;
-    let ao_iterations = 3;
+    // indirect light
+    bg_light_color: Option<Vec3>,
+    bg_light_intensity: Option<Real>,
+    ambient: Option<Ambient>,
+    gi_samples: Option<u32>,
 
+    // fog
+    fog_color: Option<Vec3>,
+    fog_density: Option<Real>,
+
+    // shadow
+    shadow_mode: Option<ShadowMode>,
+    shadow_bias: Option<Real>,
+    shadow_dist_min: Option<Real>,
+    shadow_dist_max: Option<Real>,
+    shadow_fuzziness: Option<Real>,
+    shadow_max_steps: Option<i32>,
+
+    // AO
+    ao_step_size: Option<Real>,
+    ao_intensity: Option<Real>,
+    ao_iterations: Option<i32>,
+    ao_falloff: Option<Real>,
+}
 
-    RayMarcher {
-        max_iterations,
-        max_distance,
-        accuracy,
+impl RayMarcherBuilder {
+    pub fn new(scene: DistanceField) -> Self {
+        RayMarcherBuilder {
+            scene,
 
-        debug,
+            max_iterations: None,
+            max_distance: None,
+            accuracy: None,
+            cone_tracing: None,
+            pixel_angle: None,
 
-        obj_color,
+            gamma: None,
+            tone_map: None,
+            exposure: None,
 
-        normal_accuracy,
-        offset_x,
-        offset_y,
-        offset_z,
+            obj_color: None,
+            background: None,
 
-        scene,
+            shininess: None,
+            specular_intensity: None,
 
-        light_dir,
-        light_color,
-        light_intensity,
+            reflectivity: None,
+            max_bounces: None,
+            f0: None,
 
-        bg_light_color,
-        bg_light_intensity,
+            normal_mode: None,
+            normal_accuracy: None,
 
-        shadow_dist_min,
-        shadow_dist_max,
-        shadow_fuzziness,
+            lights: None,
 
-        ao_step_size,
-        ao_intensity,
-        ao_iterations,
+            bg_light_color: None,
+            bg_light_intensity: None,
+            ambient: None,
+            gi_samples: None,
+
+            fog_color: None,
+            fog_density: None,
+
+            shadow_mode: None,
+            shadow_bias: None,
+            shadow_dist_min: None,
+            shadow_dist_max: None,
+            shadow_fuzziness: None,
+            shadow_max_steps: None,
+
+            ao_step_size: None,
+            ao_intensity: None,
+            ao_iterations: None,
+            ao_falloff: None,
+        }
+    }
+
+    pub fn max_iterations(mut self, max_iterations: i32) -> Self {
+        self.max_iterations = Some(max_iterations);
+        self
+    }
+
+    pub fn max_distance(mut self, max_distance: Real) -> Self {
+        self.max_distance = Some(max_distance);
+        self
+    }
+
+    pub fn accuracy(mut self, accuracy: Real) -> Self {
+        self.accuracy = Some(accuracy);
+        self
+    }
+
+    pub fn cone_tracing(mut self, cone_tracing: bool) -> Self {
+        self.cone_tracing = Some(cone_tracing);
+        self
+    }
+
+    pub fn pixel_angle(mut self, pixel_angle: Real) -> Self {
+        self.pixel_angle = Some(pixel_angle);
+        self
+    }
+
+    pub fn gamma(mut self, gamma: Real) -> Self {
+        self.gamma = Some(gamma);
+        self
+    }
+
+    pub fn tone_map(mut self, tone_map: ToneMap) -> Self {
+        self.tone_map = Some(tone_map);
+        self
+    }
+
+    pub fn exposure(mut self, exposure: Real) -> Self {
+        self.exposure = Some(exposure);
+        self
+    }
+
+    pub fn obj_color(mut self, obj_color: Vec3) -> Self {
+        self.obj_color = Some(obj_color);
+        self
+    }
+
+    pub fn background(mut self, background: Background) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn shininess(mut self, shininess: Real) -> Self {
+        self.shininess = Some(shininess);
+        self
+    }
+
+    pub fn specular_intensity(mut self, specular_intensity: Real) -> Self {
+        self.specular_intensity = Some(specular_intensity);
+        self
+    }
+
+    pub fn reflectivity(mut self, reflectivity: Real) -> Self {
+        self.reflectivity = Some(reflectivity);
+        self
+    }
+
+    pub fn max_bounces(mut self, max_bounces: i32) -> Self {
+        self.max_bounces = Some(max_bounces);
+        self
+    }
+
+    pub fn f0(mut self, f0: Real) -> Self {
+        self.f0 = Some(f0);
+        self
+    }
+
+    pub fn normal_mode(mut self, normal_mode: NormalMode) -> Self {
+        self.normal_mode = Some(normal_mode);
+        self
+    }
+
+    pub fn normal_accuracy(mut self, normal_accuracy: Real) -> Self {
+        self.normal_accuracy = Some(normal_accuracy);
+        self
+    }
+
+    pub fn lights(mut self, lights: Vec<Light>) -> Self {
+        self.lights = Some(lights);
+        self
+    }
+
+    // replaces the lights with a single white directional light
+    pub fn light_dir(mut self, dir: Vec3) -> Self {
+        self.lights = Some(vec![Light::Directional {
+            dir: dir.normalize(),
+            color: Vec3::new(1., 1., 1.),
+            intensity: 1.,
+        }]);
+        self
+    }
+
+    pub fn bg_light_color(mut self, bg_light_color: Vec3) -> Self {
+        self.bg_light_color = Some(bg_light_color);
+        self
+    }
+
+    pub fn bg_light_intensity(mut self, bg_light_intensity: Real) -> Self {
+        self.bg_light_intensity = Some(bg_light_intensity);
+        self
+    }
+
+    pub fn ambient(mut self, ambient: Ambient) -> Self {
+        self.ambient = Some(ambient);
+        self
+    }
+
+    pub fn gi_samples(mut self, gi_samples: u32) -> Self {
+        self.gi_samples = Some(gi_samples);
+        self
+    }
+
+    pub fn fog_color(mut self, fog_color: Vec3) -> Self {
+        self.fog_color = Some(fog_color);
+        self
+    }
+
+    pub fn fog_density(mut self, fog_density: Real) -> Self {
+        self.fog_density = Some(fog_density);
+        self
+    }
+
+    pub fn shadow_mode(mut self, shadow_mode: ShadowMode) -> Self {
+        self.shadow_mode = Some(shadow_mode);
+        self
+    }
+
+    pub fn shadow_bias(mut self, shadow_bias: Real) -> Self {
+        self.shadow_bias = Some(shadow_bias);
+        self
+    }
+
+    pub fn shadow_dist_min(mut self, shadow_dist_min: Real) -> Self {
+        self.shadow_dist_min = Some(shadow_dist_min);
+        self
+    }
+
+    pub fn shadow_dist_max(mut self, shadow_dist_max: Real) -> Self {
+        self.shadow_dist_max = Some(shadow_dist_max);
+        self
+    }
+
+    pub fn shadow_fuzziness(mut self, shadow_fuzziness: Real) -> Self {
+        self.shadow_fuzziness = Some(shadow_fuzziness);
+        self
+    }
+
+    pub fn shadow_max_steps(mut self, shadow_max_steps: i32) -> Self {
+        self.shadow_max_steps = Some(shadow_max_steps);
+        self
+    }
+
+    pub fn ao_step_size(mut self, ao_step_size: Real) -> Self {
+        self.ao_step_size = Some(ao_step_size);
+        self
+    }
+
+    pub fn ao_intensity(mut self, ao_intensity: Real) -> Self {
+        self.ao_intensity = Some(ao_intensity);
+        self
+    }
+
+    pub fn ao_iterations(mut self, ao_iterations: i32) -> Self {
+        self.ao_iterations = Some(ao_iterations);
+        self
+    }
+
+    pub fn ao_falloff(mut self, ao_falloff: Real) -> Self {
+        self.ao_falloff = Some(ao_falloff);
+        self
+    }
+
+    pub fn build(self) -> RayMarcher {
+        let max_iterations = self.max_iterations.unwrap_or(4000);
+        let max_distance = self.max_distance.unwrap_or(7.);
+        // f32 can't resolve steps as small as the f64 defaults at the size of the scenes
+        let precision_scale = if cfg!(feature = "f32") { 100. } else { 1. };
+
+        let accuracy = self.accuracy.unwrap_or(0.00001 * precision_scale);
+        let cone_tracing = self.cone_tracing.unwrap_or(false);
+        let pixel_angle = self.pixel_angle.unwrap_or(0.);
+
+        let gamma = self.gamma.unwrap_or(2.2);
+        let tone_map = self.tone_map.unwrap_or(ToneMap::None);
+        let exposure = self.exposure.unwrap_or(1.);
+
+        let obj_color = self.obj_color.unwrap_or(Vec3::new(1., 1., 1.));
+        let background = self.background.unwrap_or(Background::Solid(Vec3::zero()));
+
+        let shininess = self.shininess.unwrap_or(32.);
+        let specular_intensity = self.specular_intensity.unwrap_or(0.);
+
+        // the fresnel term alone is the full reflection
+        let reflectivity = self.reflectivity.unwrap_or(if self.f0.is_some() { 1. } else { 0. });
+        let max_bounces = self.max_bounces.unwrap_or(3);
+        let f0 = self.f0;
+
+        let normal_mode = self.normal_mode.unwrap_or(NormalMode::CentralDifferences);
+        let normal_accuracy = self.normal_accuracy.unwrap_or(0.000001 * precision_scale);
+        let offset_x = Vec3::new(normal_accuracy, 0., 0.);
+        let offset_y = Vec3::new(0., normal_accuracy, 0.);
+        let offset_z = Vec3::new(0., 0., normal_accuracy);
+
+        let lights = self.lights.unwrap_or_else(|| vec![Light::Directional {
+            dir: Vec3::new(0.5, -1., 0.5).normalize(),
+            color: Vec3::new(1., 1., 1.),
+            intensity: 1.,
+        }]);
+
+        let bg_light_color = self.bg_light_color.unwrap_or(Vec3::new(1., 1., 1.));
+        let bg_light_intensity = self.bg_light_intensity.unwrap_or(0.1);
+        let ambient = self.ambient.unwrap_or(Ambient::Flat);
+        let gi_samples = self.gi_samples.unwrap_or(0);
+
+        let fog_color = self.fog_color.unwrap_or(Vec3::new(1., 1., 1.));
+        let fog_density = self.fog_density.unwrap_or(0.);
+
+        let shadow_mode = self.shadow_mode.unwrap_or(ShadowMode::Soft);
+        let shadow_bias = self.shadow_bias.unwrap_or(accuracy * 10.);
+        let shadow_dist_min = self.shadow_dist_min.unwrap_or(0.0);
+        let shadow_dist_max = self.shadow_dist_max.unwrap_or(max_distance);
+        let shadow_fuzziness = self.shadow_fuzziness.unwrap_or(5.);
+        let shadow_max_steps = self.shadow_max_steps.unwrap_or(max_iterations);
+
+        let ao_step_size = self.ao_step_size.unwrap_or(0.05);
+        let ao_intensity = self.ao_intensity.unwrap_or(0.9);
+        let ao_iterations = self.ao_iterations.unwrap_or(3);
+        let ao_falloff = self.ao_falloff.unwrap_or(1.);
+
+        RayMarcher {
+            max_iterations,
+            max_distance,
+            accuracy,
+            cone_tracing,
+            pixel_angle,
+
+            gamma,
+            tone_map,
+            exposure,
+
+            obj_color,
+            background,
+
+            shininess,
+            specular_intensity,
+
+            reflectivity,
+            max_bounces,
+            f0,
+
+            normal_mode,
+            normal_accuracy,
+            offset_x,
+            offset_y,
+            offset_z,
+
+            scene: self.scene,
+
+            lights,
+
+            bg_light_color,
+            bg_light_intensity,
+            ambient,
+            gi_samples,
+
+            fog_color,
+            fog_density,
+
+            shadow_mode,
+            shadow_bias,
+            shadow_dist_min,
+            shadow_dist_max,
+            shadow_fuzziness,
+            shadow_max_steps,
+
+            ao_step_size,
+            ao_intensity,
+            ao_iterations,
+            ao_falloff,
+        }
     }
 }
 
 impl RayMarcher {
-    pub fn ray_marching(&self, ray: Ray) -> Vec4 {
-        let mut result: Vec4 = Vec4::one();
+    // the rng picks the directions of the global illumination rays
+    pub fn ray_marching(&self, ray: Ray, rng: &mut Rng) -> (Vec4, RayStats) {
+        self.march(ray, 0, rng)
+    }
+
+    fn march(&self, ray: Ray, bounce: i32, rng: &mut Rng) -> (Vec4, RayStats) {
+        let stats = self.trace(&ray);
+
+        // rays that ran out of iterations before hitting or leaving the scene
+        if !stats.hit && stats.iterations >= self.max_iterations {
+            return (Vec4::one(), stats);
+        }
+
+        if !stats.hit {
+            return (self.miss_color(&ray, stats.final_t), stats);
+        }
+
+        let p = ray.orig + ray.dir * stats.final_t;
+        let n = self.get_normal(&p, &ray.dir);
+
+        if bounce < self.max_bounces {
+            if let Some(Material { color, ior: Some(ior) }) = self.scene.get_material(&p) {
+                let result = self.glass(&ray, &p, &n, &color, ior, bounce, rng);
+
+                return (self.fog(&result, stats.final_t), stats);
+            }
+        }
+
+        let mut result = self.shading(&p, &n, &ray.dir);
+
+        if self.gi_samples > 0 {
+            result += Vec4::from_vec3(&(self.albedo(&p) * self.indirect_light(&p, &n, rng)), 0.);
+        }
+
+        let reflectivity = self.reflectance(&ray.dir, &n);
+
+        if reflectivity > 0. && bounce < self.max_bounces {
+            // offset the origin so the reflected ray doesn't hit the surface it starts on
+            let reflected_dir = Vec3::reflect(&ray.dir, &n);
+            let (reflected, _) = self.march(Ray::new(&(p + n * self.accuracy), &reflected_dir), bounce + 1, rng);
+
+            result = result * (1. - reflectivity) + reflected * reflectivity;
+            result.w = 1.;
+        }
+
+        (self.fog(&result, stats.final_t), stats)
+    }
+
+    // blends the reflection with the light refracted through the object, weighted by the fresnel term
+    #[allow(clippy::too_many_arguments)]
+    fn glass(&self, ray: &Ray, p: &Vec3, n: &Vec3, tint: &Vec3, ior: Real, bounce: i32, rng: &mut Rng) -> Vec4 {
+        let reflected_dir = Vec3::reflect(&ray.dir, n);
+        let (reflected, _) = self.march(Ray::new(&(p + n * self.shadow_bias), &reflected_dir), bounce + 1, rng);
+
+        let refracted_dir = match Vec3::refract(&ray.dir, n, 1. / ior) {
+            Some(dir) => dir,
+            None => return reflected,
+        };
+
+        let f0 = ((ior - 1.) / (ior + 1.)).powi(2);
+        let fresnel = schlick(Vec3::dot(&-ray.dir, n).clamp(0., 1.), f0);
+
+        let transmitted = self.march_inside(Ray::new(&(p - n * self.shadow_bias), &refracted_dir), ior, bounce + 1, rng);
+        let transmitted = Vec4::from_vec3(&(Vec3::new(transmitted.x, transmitted.y, transmitted.z) * tint), 1.);
+
+        let mut result = reflected * fresnel + transmitted * (1. - fresnel);
+        result.w = 1.;
+
+        result
+    }
+
+    // follows a ray through the inside of a transparent object until it leaves it again,
+    // rays that can't leave because of total internal reflection bounce off the inside of the surface
+    fn march_inside(&self, ray: Ray, ior: Real, bounce: i32, rng: &mut Rng) -> Vec4 {
+        let mut ray = ray;
 
-        let mut t: f64 = 0.;
+        for bounce in bounce..self.max_bounces {
+            let stats = self.trace_signed(&ray, -1.);
+
+            // the exit wasn't found, so whatever is behind the object is all that is left to show
+            if !stats.hit {
+                return self.miss_color(&ray, stats.final_t);
+            }
+
+            let p = ray.orig + ray.dir * stats.final_t;
+            // the gradient points out of the object, so flip it to face the ray
+            let n = -self.get_normal(&p, &-ray.dir);
+
+            match Vec3::refract(&ray.dir, &n, ior) {
+                Some(dir) => return self.march(Ray::new(&(p - n * self.shadow_bias), &dir), bounce + 1, rng).0,
+                None => ray = Ray::new(&(p + n * self.shadow_bias), &Vec3::reflect(&ray.dir, &n)),
+            }
+        }
+
+        // out of bounces while stuck inside
+        Vec4::new(0., 0., 0., 1.)
+    }
+
+    // marches along the ray until it hits a surface, leaves the scene or runs out of iterations, without shading
+    pub fn trace(&self, ray: &Ray) -> RayStats {
+        self.trace_signed(ray, 1.)
+    }
+
+    // sign -1 flips the distance field, so rays starting inside an object march to the point where they leave it
+    fn trace_signed(&self, ray: &Ray, sign: Real) -> RayStats {
+        let mut stats = RayStats {
+            iterations: self.max_iterations,
+            hit: false,
+            final_t: 0.,
+        };
+
+        let mut t: Real = 0.;
 
         for i in 0..self.max_iterations {
             if t > self.max_distance {
-                if self.debug {
-                    result = Vec4::one() * i as f64 / self.max_iterations as f64;
-                    break;
-                } else {
-                    result = Vec4::zero();
-                    break;
-                }
+                stats.iterations = i;
+                break;
             }
 
             let p = ray.orig + ray.dir * t;
-            let d = self.distance_field(&p);
+            let d = self.distance_field(&p) * sign;
 
-            if d < self.accuracy {
-                if self.debug {
-                    result = Vec4::one() * i as f64 / self.max_iterations as f64;
-                    break;
-                } else {
-                    result = self.shading(&p);
-                    break;
-                }
+            if d < self.hit_threshold(t) {
+                stats.iterations = i;
+                stats.hit = true;
+                break;
             }
 
             t += d;
         }
 
-        result
+        stats.final_t = t;
+
+        stats
+    }
+
+    // the background seen along a ray that left the scene after traveling t
+    pub fn miss_color(&self, ray: &Ray, t: Real) -> Vec4 {
+        self.fog(&Vec4::from_vec3(&self.background.sample(&ray.dir), 0.), t)
+    }
+
+    // true when no ray inside the cone with the given half angle around ray can hit anything before max_distance.
+    // this relies on the distance field never overestimating the distance, fields that are only bounds
+    // (fractals, twists, displacements) can still hide thin features inside a cone that looks empty
+    pub fn cone_is_empty(&self, ray: &Ray, angle: Real) -> bool {
+        let mut t: Real = 0.;
+
+        for _ in 0..self.max_iterations {
+            if t > self.max_distance {
+                return true;
+            }
+
+            let d = self.distance_field(&(ray.orig + ray.dir * t));
+            let radius = self.accuracy + t * angle;
+
+            if d < radius {
+                return false;
+            }
+
+            // short enough that the whole cone section stays accuracy inside the empty sphere around the current point
+            t += (d - radius) / (1. + angle);
+        }
+
+        false
+    }
+
+    // share of the reflected color, with f0 set it rises towards reflectivity at grazing angles
+    fn reflectance(&self, view_dir: &Vec3, n: &Vec3) -> Real {
+        match self.f0 {
+            Some(f0) => self.reflectivity * schlick(Vec3::dot(&-view_dir, n).clamp(0., 1.), f0),
+            None => self.reflectivity,
+        }
+    }
+
+    // accuracy * (1 + t * pixel_angle / accuracy), which is the radius of the pixel cone at t plus the accuracy
+    fn hit_threshold(&self, t: Real) -> Real {
+        if self.cone_tracing {
+            self.accuracy + t * self.pixel_angle
+        } else {
+            self.accuracy
+        }
+    }
+
+    // blend towards the fog color the further the ray traveled
+    fn fog(&self, color: &Vec4, t: Real) -> Vec4 {
+        if self.fog_density <= 0. {
+            return *color;
+        }
+
+        let f = 1. - (-self.fog_density * t).exp();
+
+        Vec4::new(
+            color.x * (1. - f) + self.fog_color.x * f,
+            color.y * (1. - f) + self.fog_color.y * f,
+            color.z * (1. - f) + self.fog_color.z * f,
+            color.w,
+        )
+    }
+
+    // scales the color channels by the exposure, the coverage in w is left untouched
+    pub fn expose(&self, color: &Vec4) -> Vec4 {
+        Vec4::new(color.x * self.exposure, color.y * self.exposure, color.z * self.exposure, color.w)
     }
 
-    fn distance_field(&self, p: &Vec3) -> f64 {
+    pub fn post_processing(&self, color: &Vec4) -> Vec4 {
+        let color = self.expose(color);
+
+        Vec4::new(
+            self.tone_map.apply(color.x),
+            self.tone_map.apply(color.y),
+            self.tone_map.apply(color.z),
+            color.w,
+        )
+    }
+
+    fn distance_field(&self, p: &Vec3) -> Real {
         self.scene.get_distance(p)
     }
 
-    fn get_normal(&self, p: &Vec3) -> Vec3 {
-        Vec3::new(
-            self.distance_field(&(p + self.offset_x)) - self.distance_field(&(p - self.offset_x)),
-            self.distance_field(&(p + self.offset_y)) - self.distance_field(&(p - self.offset_y)),
-            self.distance_field(&(p + self.offset_z)) - self.distance_field(&(p - self.offset_z)),
-        ).normalize()
+    pub fn get_normal(&self, p: &Vec3, ray_dir: &Vec3) -> Vec3 {
+        let gradient = self.scene.get_normal_analytic(p).unwrap_or_else(|| self.get_gradient(p));
+        let length = gradient.length();
+
+        // fractals can have flat or broken gradients, normalizing those would result in NaN colors,
+        // so we use a normal facing the viewer instead
+        if !(length > 1e-12 && length.is_finite()) {
+            return -ray_dir;
+        }
+
+        gradient / length
+    }
+
+    fn get_gradient(&self, p: &Vec3) -> Vec3 {
+        match self.normal_mode {
+            NormalMode::CentralDifferences => Vec3::new(
+                self.distance_field(&(p + self.offset_x)) - self.distance_field(&(p - self.offset_x)),
+                self.distance_field(&(p + self.offset_y)) - self.distance_field(&(p - self.offset_y)),
+                self.distance_field(&(p + self.offset_z)) - self.distance_field(&(p - self.offset_z)),
+            ),
+            // https://iquilezles.org/articles/normalsSDF
+            NormalMode::Tetrahedron => {
+                let k0 = Vec3::new(1., -1., -1.);
+                let k1 = Vec3::new(-1., -1., 1.);
+                let k2 = Vec3::new(-1., 1., -1.);
+                let k3 = Vec3::new(1., 1., 1.);
+
+                k0 * self.distance_field(&(p + k0 * self.normal_accuracy))
+                    + k1 * self.distance_field(&(p + k1 * self.normal_accuracy))
+                    + k2 * self.distance_field(&(p + k2 * self.normal_accuracy))
+                    + k3 * self.distance_field(&(p + k3 * self.normal_accuracy))
+            }
+        }
+    }
+
+    fn albedo(&self, p: &Vec3) -> Vec3 {
+        self.scene.get_material(p).map_or(self.obj_color, |m| m.color)
+    }
+
+    // one diffuse bounce, the gathered surfaces are only lit directly. the cosine weighting of the directions
+    // cancels the cosine of the lambert term, so the estimate is just the average of the incoming light
+    fn indirect_light(&self, p: &Vec3, n: &Vec3, rng: &mut Rng) -> Vec3 {
+        // pick a tangent that isn't parallel to the normal, the bitangent completes the basis
+        let reference = if n.z.abs() < 0.9 { Vec3::new(0., 0., 1.) } else { Vec3::new(1., 0., 0.) };
+        let tangent = Vec3::cross(n, &reference).normalize();
+        let bitangent = Vec3::cross(&tangent, n);
+
+        let origin = p + n * self.shadow_bias;
+        let mut light = Vec3::zero();
+
+        for _ in 0..self.gi_samples {
+            // points on the unit disk projected up onto the hemisphere are cosine distributed
+            let (x, y) = rng.in_unit_disk();
+            let dir = tangent * x + bitangent * y + n * (1. - x * x - y * y).max(0.).sqrt();
+
+            let ray = Ray::new(&origin, &dir);
+            let stats = self.trace(&ray);
+
+            let incoming = if stats.hit {
+                let hit_p = ray.orig + ray.dir * stats.final_t;
+                self.shading(&hit_p, &self.get_normal(&hit_p, &ray.dir), &ray.dir)
+            } else {
+                self.miss_color(&ray, stats.final_t)
+            };
+
+            light += Vec3::new(incoming.x, incoming.y, incoming.z);
+        }
+
+        light / self.gi_samples as Real
     }
 
-    fn shading(&self, p: &Vec3) -> Vec4 {
-        let n = self.get_normal(&p);
-        let shadow = self.shadow(&p, &n);
-        let ambient_occlusion = self.ambient_occlusion(&p, &n);
+    fn shading(&self, p: &Vec3, n: &Vec3, view_dir: &Vec3) -> Vec4 {
+        let obj_color = self.albedo(p);
 
         // Vec4::from_vec3(&n, 1.)
 
-        let sun_light = self.obj_color * (self.light_color * Vec3::dot(&(-self.light_dir), &n).clamp(0., 1.) * self.light_intensity * shadow);
-        let bg_light = self.obj_color * (self.bg_light_color * self.bg_light_intensity) * ambient_occlusion;
+        // the light gathered by global illumination replaces the ambient approximation
+        let mut light = if self.gi_samples > 0 {
+            Vec3::zero()
+        } else {
+            obj_color * (self.ambient_color(n) * self.bg_light_intensity) * self.ambient_occlusion(p, n)
+        };
 
-        let light = sun_light + bg_light;
+        for l in &self.lights {
+            let (light_dir, _, light_color) = l.illuminate(p, self.accuracy);
+            let shadow = match l {
+                Light::Directional { .. } => self.shadow(p, n, &light_dir, self.shadow_dist_max),
+                Light::Point { pos, .. } => self.shadow_towards(p, n, pos),
+            };
+
+            light += obj_color * (light_color * Vec3::dot(&light_dir, n).clamp(0., 1.) * shadow);
+
+            // blinn-phong highlight from the half vector between light and eye
+            if self.specular_intensity > 0. {
+                let half_dir = (light_dir - view_dir).normalize();
+                let specular = Vec3::dot(n, &half_dir).max(0.).powf(self.shininess);
+
+                light += light_color * (specular * self.specular_intensity * shadow);
+            }
+        }
 
         Vec4::from_vec3(&light, 1.)
     }
 
-    fn shadow(&self, p: &Vec3, n: &Vec3) -> f64 {
-        let sro = p + n * self.accuracy;
-        let sr = Ray::new(&sro, &(-self.light_dir));
+    fn ambient_color(&self, n: &Vec3) -> Vec3 {
+        match self.ambient {
+            Ambient::Flat => self.bg_light_color,
+            Ambient::Hemisphere { sky, ground } => Vec3::lerp(&ground, &sky, 0.5 * (n.y + 1.)),
+        }
+    }
+
+    fn shadow(&self, p: &Vec3, n: &Vec3, light_dir: &Vec3, max_dist: Real) -> Real {
+        if let ShadowMode::None = self.shadow_mode {
+            return 1.;
+        }
 
-        let mut t: f64 = self.shadow_dist_min;
-        let mut result: f64 = 1.0;
+        let sro = p + n * self.shadow_bias;
+        let sr = Ray::new(&sro, light_dir);
+
+        let mut t: Real = self.shadow_dist_min;
+        let mut result: Real = 1.0;
+
+        // rays grazing a surface take tiny steps, so the number of steps is capped
+        for _ in 0..self.shadow_max_steps {
+            if t >= max_dist {
+                break;
+            }
 
-        while t < self.shadow_dist_max {
             let p = sr.orig + sr.dir * t;
             let d = self.distance_field(&p);
 
@@ -183,25 +940,338 @@ impl RayMarcher {
                 return 0.;
             }
 
-            result = result.min(self.shadow_fuzziness * d / t);
+            if let ShadowMode::Soft = self.shadow_mode {
+                result = result.min(self.shadow_fuzziness * d / t);
+            }
             t += d;
         }
 
-        return result.clamp(0., 1.);
+        result.clamp(0., 1.)
+    }
+
+    // shadow ray that stops at the target, so geometry behind a point light doesn't occlude it
+    fn shadow_towards(&self, p: &Vec3, n: &Vec3, target: &Vec3) -> Real {
+        let to_target = target - (p + n * self.shadow_bias);
+
+        self.shadow(p, n, &to_target.normalize(), to_target.length())
     }
 
-    fn ambient_occlusion(&self, p: &Vec3, n: &Vec3) -> f64 {
-        let mut ao: f64 = 0.0;
-        let mut dist: f64;
+    fn ambient_occlusion(&self, p: &Vec3, n: &Vec3) -> Real {
+        if self.ao_iterations <= 0 {
+            return 1.;
+        }
+
+        let mut ao: Real = 0.0;
+        let mut dist: Real;
 
         for i in 0..self.ao_iterations {
-            dist = self.ao_step_size * (i + 1) as f64;
-            let point = p + &(n * dist);
+            dist = self.ao_step_size * (i as Real + 1.);
+            let point = p + (n * dist);
 
-            ao += f64::max(0.0, (dist - self.distance_field(&point)) / dist);
+            // each sample is in [0, 1], points inside a surface count as fully occluded
+            ao += ((dist - self.distance_field(&point)) / dist).clamp(0., 1.);
         }
 
-        (1.0 - ao * self.ao_intensity).clamp(0., 1.)
+        // average the samples, so the result doesn't depend on the number of iterations
+        ao /= self.ao_iterations as Real;
+
+        (1.0 - ao * self.ao_intensity).clamp(0., 1.).powf(self.ao_falloff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::distance_fields::{CheckerPlane, Cuboid, Plane, Sphere, Union, WithMaterial};
+
+    fn checker_plane() -> DistanceField {
+        DistanceField::CheckerPlane(CheckerPlane {
+            normal: Vec3::new(0., 1., 0.),
+            h: 0.3,
+            scale: 0.5,
+            color_a: Vec3::new(0.9, 0.9, 0.9),
+            color_b: Vec3::new(0.2, 0.2, 0.2),
+        })
+    }
+
+    fn glass_scene(tint: Vec3, ior: Real) -> DistanceField {
+        DistanceField::Union(Box::new(Union {
+            a: DistanceField::WithMaterial(Box::new(WithMaterial {
+                a: DistanceField::Sphere(Sphere { pos: Vec3::new(0., 0.2, -2.), size: 0.5 }),
+                material: Material { color: tint, ior: Some(ior) },
+            })),
+            b: checker_plane(),
+        }))
+    }
+
+    fn glass_ray_marcher(scene: DistanceField) -> RayMarcher {
+        // without shadows and AO the shading of the plane doesn't depend on the sphere
+        RayMarcherBuilder::new(scene).shadow_mode(ShadowMode::None).ao_iterations(0).build()
+    }
+
+    #[test]
+    fn glass_with_the_ior_of_air_is_invisible() {
+        // straight down through the center of the sphere onto the checker plane
+        let ray = Ray::new(&Vec3::new(0.1, 2., -2.), &Vec3::new(0., -1., 0.));
+        let mut rng = Rng::new(0);
+
+        let (through_glass, _) = glass_ray_marcher(glass_scene(Vec3::new(1., 1., 1.), 1.)).ray_marching(ray, &mut rng);
+        let (direct, _) = glass_ray_marcher(checker_plane()).ray_marching(ray, &mut rng);
+
+        assert!(through_glass.approx_eq(&direct, 1e-3), "{through_glass:?} != {direct:?}");
+
+        // the light passing through is tinted by the color of the glass
+        let (tinted, _) = glass_ray_marcher(glass_scene(Vec3::new(0.5, 0.25, 1.), 1.)).ray_marching(ray, &mut rng);
+
+        assert!(tinted.approx_eq(&(direct * Vec4::new(0.5, 0.25, 1., 1.)), 1e-3), "{tinted:?}");
+    }
+
+    #[test]
+    fn glass_sphere_over_checker_plane_bends_the_rays() {
+        // off center the refraction shows a different part of the plane than the ray would hit on its own
+        let ray = Ray::new(&Vec3::new(0.3, 2., -2.), &Vec3::new(0., -1., 0.));
+        let mut rng = Rng::new(0);
+
+        let (through_glass, stats) = glass_ray_marcher(glass_scene(Vec3::new(1., 1., 1.), 1.5)).ray_marching(ray, &mut rng);
+        let (direct, _) = glass_ray_marcher(checker_plane()).ray_marching(ray, &mut rng);
+
+        assert!(stats.hit);
+        assert_eq!(through_glass.w, 1.);
+        assert!(!through_glass.approx_eq(&direct, 1e-3), "{through_glass:?} == {direct:?}");
+    }
+
+    #[test]
+    fn global_illumination_replaces_the_ambient_light() {
+        // the bounce rays of a lone sphere all escape into the black background
+        let sphere = || DistanceField::Sphere(Sphere { pos: Vec3::new(0., 0., -2.), size: 0.5 });
+        let ray = Ray::new(&Vec3::new(0., 0., 0.), &Vec3::new(0.1, 0.2, -1.));
+        let mut rng = Rng::new(0);
+
+        let (gi, _) = RayMarcherBuilder::new(sphere()).gi_samples(8).build().ray_marching(ray, &mut rng);
+        let (ambient, _) = RayMarcherBuilder::new(sphere()).build().ray_marching(ray, &mut rng);
+        let (direct, _) = RayMarcherBuilder::new(sphere()).bg_light_intensity(0.).build().ray_marching(ray, &mut rng);
+
+        assert!(gi.approx_eq(&direct, 1e-6), "{gi:?} != {direct:?}");
+        assert!(ambient.x > direct.x);
+    }
+
+    #[test]
+    fn schlick_is_f0_at_normal_incidence_and_1_at_grazing() {
+        assert_eq!(schlick(1., 0.04), 0.04);
+        assert_eq!(schlick(0., 0.04), 1.);
+        assert!(schlick(0.5, 0.04) > 0.04 && schlick(0.5, 0.04) < 1.);
+    }
+
+    #[test]
+    fn reflectivity_scales_the_fresnel_term() {
+        let sphere = || DistanceField::Sphere(Sphere { pos: Vec3::new(0., 0., -2.), size: 0.5 });
+        let n = Vec3::new(0., 0., 1.);
+        let view_dir = Vec3::new(0., 0., -1.);
+
+        let fresnel = RayMarcherBuilder::new(sphere()).f0(0.04).build();
+        assert_eq!(fresnel.reflectance(&view_dir, &n), 0.04);
+
+        let scaled = RayMarcherBuilder::new(sphere()).f0(0.04).reflectivity(0.5).build();
+        assert_eq!(scaled.reflectance(&view_dir, &n), 0.02);
+        assert_eq!(scaled.reflectance(&Vec3::new(1., 0., 0.), &n), 0.5);
+    }
+
+    #[test]
+    fn tetrahedron_and_central_difference_normals_agree_on_a_sphere() {
+        let sphere = DistanceField::Sphere(Sphere { pos: Vec3::new(0., 0.2, -2.), size: 0.5 });
+        let central = RayMarcherBuilder::new(sphere.clone()).normal_mode(NormalMode::CentralDifferences).build();
+        let tetrahedron = RayMarcherBuilder::new(sphere).normal_mode(NormalMode::Tetrahedron).build();
+
+        let mut rng = Rng::new(43);
+
+        for _ in 0..100 {
+            let (x, y) = rng.in_unit_disk();
+            let n = Vec3::new(x, y, rng.range(-1., 1.)).normalize();
+            let p = Vec3::new(0., 0.2, -2.) + n * 0.5;
+
+            // the sphere has an analytic normal, so compare the numeric gradients directly
+            let a = central.get_gradient(&p).normalize();
+            let b = tetrahedron.get_gradient(&p).normalize();
+
+            assert!(a.approx_eq(&n, 1e-3), "{a:?} vs {n:?}");
+            assert!(b.approx_eq(&n, 1e-3), "{b:?} vs {n:?}");
+        }
+    }
+
+    #[test]
+    fn flat_gradients_fall_back_to_a_normal_facing_the_viewer() {
+        // at the center of a cube all samples are the same distance away from the faces
+        let cube = DistanceField::Cuboid(Cuboid { pos: Vec3::zero(), size: Vec3::new(1., 1., 1.) });
+        let ray_dir = Vec3::new(1., -2., 2.).normalize();
+
+        for normal_mode in [NormalMode::CentralDifferences, NormalMode::Tetrahedron] {
+            let ray_marcher = RayMarcherBuilder::new(cube.clone()).normal_mode(normal_mode).build();
+            let p = Vec3::zero();
+            assert_eq!(ray_marcher.get_gradient(&p).length(), 0.);
+
+            let n = ray_marcher.get_normal(&p, &ray_dir);
+            assert!(n.x.is_finite() && n.y.is_finite() && n.z.is_finite());
+            assert!((n.length() - 1.).abs() < 1e-6);
+            assert!(n.approx_eq(&-ray_dir, 0.));
+        }
+    }
+
+    fn sphere_above_plane() -> DistanceField {
+        DistanceField::Union(Box::new(Union {
+            a: DistanceField::Sphere(Sphere { pos: Vec3::new(0., 0.2, -2.), size: 0.5 }),
+            b: DistanceField::Plane(Plane { normal: Vec3::new(0., 1., 0.), h: 0.3 }),
+        }))
+    }
+
+    #[test]
+    fn plane_is_not_self_shadowed() {
+        let up = Vec3::new(0., 1., 0.);
+        let light_dir = Vec3::new(0.3, 1., 0.2).normalize();
+
+        for shadow_mode in [ShadowMode::Hard, ShadowMode::Soft] {
+            let ray_marcher = RayMarcherBuilder::new(sphere_above_plane()).shadow_mode(shadow_mode).build();
+
+            for x in -6..=6 {
+                for z in -8..=2 {
+                    let p = Vec3::new(x as Real * 0.5, -0.3, z as Real * 0.5 - 1.);
+                    // everything outside of the shadow of the sphere is lit
+                    if Vec3::new(p.x, 0., p.z + 2.).length() > 1.5 {
+                        assert_eq!(ray_marcher.shadow(&p, &up, &light_dir, ray_marcher.shadow_dist_max), 1., "at {p:?}");
+                    }
+                }
+            }
+
+            let below = Vec3::new(0., -0.3, -2.);
+            assert_eq!(ray_marcher.shadow(&below, &up, &light_dir, ray_marcher.shadow_dist_max), 0.);
+        }
+    }
+
+    #[test]
+    fn shadow_without_bias_has_acne() {
+        let ray_marcher = RayMarcherBuilder::new(sphere_above_plane()).shadow_mode(ShadowMode::Hard).shadow_bias(0.).build();
+        let p = Vec3::new(2., -0.3, 0.);
+
+        // the shadow ray starts on the surface and hits it right away
+        assert_eq!(ray_marcher.shadow(&p, &Vec3::new(0., 1., 0.), &Vec3::new(0., 1., 0.), ray_marcher.shadow_dist_max), 0.);
+    }
+
+    #[test]
+    fn shadow_gives_up_after_the_max_steps() {
+        let ray_marcher = RayMarcherBuilder::new(sphere_above_plane()).shadow_mode(ShadowMode::Hard).shadow_max_steps(0).build();
+        let below = Vec3::new(0., -0.3, -2.);
+
+        assert_eq!(ray_marcher.shadow(&below, &Vec3::new(0., 1., 0.), &Vec3::new(0., 1., 0.), ray_marcher.shadow_dist_max), 1.);
+    }
+
+    #[test]
+    fn shadow_modes_on_a_simple_occluder() {
+        let up = Vec3::new(0., 1., 0.);
+        let below = Vec3::new(0., -0.3, -2.);
+        // passes the sphere at a distance of 0.05
+        let past = Vec3::new(0.55, -0.3, -2.);
+
+        let shadow = |shadow_mode: ShadowMode, p: &Vec3| {
+            let ray_marcher = RayMarcherBuilder::new(sphere_above_plane()).shadow_mode(shadow_mode).build();
+            ray_marcher.shadow(p, &up, &up, ray_marcher.shadow_dist_max)
+        };
+
+        assert_eq!(shadow(ShadowMode::None, &below), 1.);
+        assert_eq!(shadow(ShadowMode::None, &past), 1.);
+
+        assert_eq!(shadow(ShadowMode::Hard, &below), 0.);
+        assert_eq!(shadow(ShadowMode::Hard, &past), 1.);
+
+        // the soft shadow gets darker the closer the ray passes the occluder
+        assert_eq!(shadow(ShadowMode::Soft, &below), 0.);
+        let penumbra = shadow(ShadowMode::Soft, &past);
+        assert!(penumbra > 0. && penumbra < 1., "{penumbra}");
+        assert!(shadow(ShadowMode::Soft, &Vec3::new(0.52, -0.3, -2.)) < penumbra);
+    }
+
+    #[test]
+    fn shadows_are_soft_by_default() {
+        assert!(matches!(create_ray_marcher(sphere_above_plane()).shadow_mode, ShadowMode::Soft));
+    }
+
+    #[test]
+    fn ambient_occlusion_of_an_isolated_sphere_is_1() {
+        let ray_marcher = RayMarcherBuilder::new(DistanceField::Sphere(Sphere { pos: Vec3::zero(), size: 1. })).ao_iterations(5).build();
+        let n = Vec3::new(1., 2., -2.).normalize();
+
+        assert!((ray_marcher.ambient_occlusion(&n, &n) - 1.).abs() < 1e-3);
+    }
+
+    #[test]
+    fn ambient_occlusion_stays_in_0_to_1() {
+        let up = Vec3::new(0., 1., 0.);
+        // right below the sphere the samples run into it
+        let below = Vec3::new(0., -0.3, -2.);
+
+        for ao_iterations in [1, 10, 1000, 100000] {
+            for ao_intensity in [0.5, 1., 20.] {
+                let ray_marcher = RayMarcherBuilder::new(sphere_above_plane())
+                    .ao_iterations(ao_iterations)
+                    .ao_intensity(ao_intensity)
+                    .ao_falloff(2.)
+                    .build();
+
+                let ao = ray_marcher.ambient_occlusion(&below, &up);
+                assert!((0. ..=1.).contains(&ao), "{ao} for {ao_iterations} iterations");
+            }
+        }
+    }
+
+    #[test]
+    fn ambient_occlusion_falloff_darkens_occluded_points() {
+        let up = Vec3::new(0., 1., 0.);
+        let below = Vec3::new(0., -0.3, -2.);
+
+        let ao = |ao_falloff: Real| {
+            RayMarcherBuilder::new(sphere_above_plane()).ao_iterations(10).ao_step_size(0.1).ao_falloff(ao_falloff).build().ambient_occlusion(&below, &up)
+        };
+
+        assert!(ao(1.) < 1.);
+        assert!((ao(2.) - ao(1.).powi(2)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn exposure_2_doubles_the_channels_before_clamping() {
+        let ray = Ray::new(&Vec3::new(0., 2., -2.), &Vec3::new(0., -1., 0.));
+        let builder = || RayMarcherBuilder::new(sphere_above_plane()).tone_map(ToneMap::None);
+        let normal = builder().build();
+        let doubled = builder().exposure(2.).build();
+
+        // the exposure isn't part of the shading itself
+        let (color, _) = normal.ray_marching(ray, &mut Rng::new(0));
+        let (color_doubled, _) = doubled.ray_marching(ray, &mut Rng::new(0));
+        assert!(color.approx_eq(&color_doubled, 0.));
+        assert!(color.x > 0.);
+
+        let exposed = doubled.post_processing(&color);
+        let expected = normal.post_processing(&color) * 2.;
+        assert!(exposed.approx_eq(&Vec4::new(expected.x, expected.y, expected.z, color.w), 1e-6));
+    }
+
+    #[test]
+    fn exposure_is_1_by_default() {
+        let color = Vec4::new(0.2, 0.5, 3., 0.7);
+
+        assert!(create_ray_marcher(sphere_above_plane()).expose(&color).approx_eq(&color, 0.));
+    }
+
+    #[test]
+    fn point_light_on_the_surface_gives_finite_shading() {
+        // the light sits right on the top of the sphere
+        let top = Vec3::new(0., 0.7, -2.);
+        let ray_marcher = RayMarcherBuilder::new(sphere_above_plane())
+            .lights(vec![Light::Point { pos: top, color: Vec3::new(1., 1., 1.), intensity: 1. }])
+            .build();
+
+        let n = Vec3::new(0., 1., 0.);
+        for p in [top, top + Vec3::new(0.01, 0., 0.)] {
+            let color = ray_marcher.shading(&p, &n, &Vec3::new(0., -1., 0.));
+            assert!(color.x.is_finite() && color.y.is_finite() && color.z.is_finite(), "{color:?} at {p:?}");
+        }
     }
 }
 
@@ -0,0 +1,947 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use image::{DynamicImage, ImageBuffer, ImageFormat, Luma, Rgb, Rgb32FImage, Rgba, RgbaImage, RgbImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::{Camera, Projection};
+use crate::ray::Ray;
+use crate::ray_marching::{RayMarcher, RayStats};
+use crate::real::{self, Real};
+use crate::rng::Rng;
+use crate::vec3::Vec3;
+use crate::vec4::Vec4;
+
+const TILE_SIZE: u32 = 32;
+
+#[derive(Debug, Clone, Copy)]
+pub enum RenderPass {
+    // the shaded image
+    Color,
+    // distance to the hit surface
+    Depth,
+    // world space surface normals
+    Normal,
+    // number of march iterations per ray, from blue (few) to red (most in the image)
+    Heatmap,
+    // the shaded image with lines where the depth or the normal jumps between neighboring pixels
+    Edges,
+}
+
+// file format of the color image, the other passes are always written as png
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    // 8 bit, tone mapped and gamma corrected
+    Png,
+    // 32 bit float, the linear colors before tone mapping
+    Exr,
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Exr => "exr",
+        }
+    }
+
+    pub fn image_format(&self) -> ImageFormat {
+        match self {
+            OutputFormat::Png => ImageFormat::Png,
+            OutputFormat::Exr => ImageFormat::OpenExr,
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(OutputFormat::Png),
+            "exr" => Ok(OutputFormat::Exr),
+            _ => Err(format!("unknown format '{s}'")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RenderSettings {
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub threads: Option<usize>,
+    // size in pixels of the blocks a coarse pre-pass checks for being empty, the color pass fills those
+    // with the background without marching them. None disables the pre-pass
+    pub background_blocks: Option<u32>,
+    // the random numbers of every pixel are derived from the seed, renders with the same seed are identical
+    #[serde(default)]
+    pub seed: u64,
+    // index of the frame in an animation, the samples of a pixel differ from frame to frame
+    #[serde(skip)]
+    pub frame: u32,
+    #[serde(default)]
+    pub edges: EdgeSettings,
+    // smooths the noise of renders with few samples, None leaves the color as it is
+    pub denoise: Option<DenoiseSettings>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EdgeSettings {
+    // relative difference of the depths of two neighboring pixels that counts as an edge
+    pub depth_threshold: Real,
+    // 1 - cos of the angle between the normals of two neighboring pixels that counts as a crease
+    pub normal_threshold: Real,
+    // linear color of the lines
+    pub color: Vec3,
+}
+
+// a cross bilateral filter over the linear colors, neighbors at a different depth or facing another way
+// than the pixel contribute less, so the filter doesn't blur across silhouettes and creases
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DenoiseSettings {
+    // standard deviation in pixels of the distance weight
+    pub spatial_sigma: Real,
+    // standard deviation of the weight of the color difference, smaller values keep more detail
+    pub range_sigma: Real,
+    // standard deviation of the weight of the relative depth difference
+    pub depth_sigma: Real,
+    // the cos of the angle between the normals is raised to this power, larger values keep creases sharper
+    pub normal_exponent: Real,
+}
+
+impl Default for DenoiseSettings {
+    fn default() -> Self {
+        DenoiseSettings {
+            spatial_sigma: 1.5,
+            range_sigma: 0.2,
+            depth_sigma: 0.02,
+            normal_exponent: 16.,
+        }
+    }
+}
+
+impl Default for EdgeSettings {
+    fn default() -> Self {
+        EdgeSettings {
+            depth_threshold: 0.05,
+            normal_threshold: 0.3,
+            color: Vec3::zero(),
+        }
+    }
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            width: 1920,
+            height: 1080,
+            samples: 1,
+            threads: None,
+            background_blocks: None,
+            seed: 0,
+            frame: 0,
+            edges: EdgeSettings::default(),
+            denoise: None,
+        }
+    }
+}
+
+impl RenderSettings {
+    pub fn aspect_ratio(&self) -> Real {
+        self.width as Real / self.height as Real
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width < 2 || self.height < 2 {
+            return Err(String::from("width and height must be at least 2"));
+        }
+
+        if self.threads == Some(0) {
+            return Err(String::from("threads must be at least 1"));
+        }
+
+        if self.samples == 0 {
+            return Err(String::from("samples must be at least 1"));
+        }
+
+        if self.background_blocks == Some(0) {
+            return Err(String::from("background blocks must be at least 1 pixel"));
+        }
+
+        if let Some(denoise) = &self.denoise {
+            if denoise.spatial_sigma <= 0. || denoise.range_sigma <= 0. || denoise.depth_sigma <= 0. {
+                return Err(String::from("denoise sigmas must be greater than 0"));
+            }
+
+            if denoise.normal_exponent < 0. {
+                return Err(String::from("denoise normal exponent must not be negative"));
+            }
+        }
+
+        Ok(())
+    }
+
+    // the checks that depend on the camera the settings are rendered with
+    pub fn validate_for(&self, camera: &Camera) -> Result<(), String> {
+        if camera.projection == Projection::Panoramic && self.width != self.height * 2 {
+            return Err(format!("panoramic images must be twice as wide as they are high, not {}x{}", self.width, self.height));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RenderStats {
+    pub rays: u64,
+    pub iterations: u64,
+    pub hits: u64,
+    pub starved: u64,
+    // blocks of the background pre-pass, and how many of them were filled without marching
+    pub background_blocks: u64,
+    pub skipped_blocks: u64,
+}
+
+impl RenderStats {
+    fn add_ray(&mut self, ray_stats: &RayStats, max_iterations: i32) {
+        self.rays += 1;
+        self.iterations += ray_stats.iterations as u64;
+
+        if ray_stats.hit {
+            self.hits += 1;
+        }
+
+        if ray_stats.iterations >= max_iterations {
+            self.starved += 1;
+        }
+    }
+
+    pub fn merge(a: RenderStats, b: RenderStats) -> RenderStats {
+        RenderStats {
+            rays: a.rays + b.rays,
+            iterations: a.iterations + b.iterations,
+            hits: a.hits + b.hits,
+            starved: a.starved + b.starved,
+            background_blocks: a.background_blocks + b.background_blocks,
+            skipped_blocks: a.skipped_blocks + b.skipped_blocks,
+        }
+    }
+}
+
+// renders the color image in memory, tone mapped and gamma corrected like the png the binary writes
+pub fn render(ray_marcher: &RayMarcher, camera: &Camera, settings: &RenderSettings) -> Result<(RgbImage, RenderStats), String> {
+    let (image, stats) = render_pass(ray_marcher, camera, settings, RenderPass::Color, OutputFormat::Png, false, &|_, _| {})?;
+
+    Ok((image.into_rgb8(), stats))
+}
+
+// progress is called from the render threads with the number of finished and total tiles every time a tile
+// is done. passes that need several buffers start over for each of them
+#[allow(clippy::too_many_arguments)]
+pub fn render_pass(
+    ray_marcher: &RayMarcher,
+    camera: &Camera,
+    settings: &RenderSettings,
+    pass: RenderPass,
+    format: OutputFormat,
+    alpha: bool,
+    progress: &(dyn Fn(u32, u32) + Sync),
+) -> Result<(DynamicImage, RenderStats), String> {
+    settings.validate()?;
+    settings.validate_for(camera)?;
+
+    let index = |x: u32, y: u32| (y * settings.width + x) as usize;
+    let mut stats = RenderStats::default();
+
+    let image = match pass {
+        RenderPass::Color => {
+            let pixels = color_pixels(ray_marcher, camera, settings, None, progress, &mut stats)?;
+
+            match format {
+                // the background is left out of the color, so it can be composited over something else
+                OutputFormat::Png if alpha => DynamicImage::ImageRgba8(RgbaImage::from_fn(settings.width, settings.height, |x, y| {
+                    let pixel = pixels[index(x, y)];
+                    let color = Vec4::from_vec3(&pixel.hit_color, pixel.color.w);
+                    Rgba(ray_marcher.post_processing(&color).to_pixel_data_alpha(ray_marcher.gamma))
+                })),
+                OutputFormat::Png => DynamicImage::ImageRgb8(RgbImage::from_fn(settings.width, settings.height, |x, y| {
+                    Rgb(ray_marcher.post_processing(&pixels[index(x, y)].color).to_pixel_data(ray_marcher.gamma))
+                })),
+                OutputFormat::Exr => DynamicImage::ImageRgb32F(Rgb32FImage::from_fn(settings.width, settings.height, |x, y| {
+                    let color = ray_marcher.expose(&pixels[index(x, y)].color);
+                    // the casts are no-ops with the f32 feature
+                    #[allow(clippy::unnecessary_cast)]
+                    let pixel = [color.x as f32, color.y as f32, color.z as f32];
+                    Rgb(pixel)
+                })),
+            }
+        }
+        RenderPass::Depth => {
+            let pixels = render_pixels(settings, progress, &mut stats, |i, j| calc_depth(ray_marcher, camera, settings, i, j))?;
+
+            DynamicImage::ImageLuma16(ImageBuffer::from_fn(settings.width, settings.height, |x, y| Luma([pixels[index(x, y)]])))
+        }
+        RenderPass::Normal => {
+            let pixels = render_pixels(settings, progress, &mut stats, |i, j| calc_normal(ray_marcher, camera, settings, i, j))?;
+
+            DynamicImage::ImageRgb8(RgbImage::from_fn(settings.width, settings.height, |x, y| Rgb(pixels[index(x, y)])))
+        }
+        RenderPass::Heatmap => {
+            let pixels = render_pixels(settings, progress, &mut stats, |i, j| calc_iterations(ray_marcher, camera, settings, i, j))?;
+
+            // normalize by the slowest ray in the image, the iteration limit is usually far off
+            let max_iterations = pixels.iter().copied().max().unwrap_or(0).max(1);
+
+            DynamicImage::ImageRgb8(RgbImage::from_fn(settings.width, settings.height, |x, y| {
+                let t = pixels[index(x, y)] as Real / max_iterations as Real;
+                Rgb(Vec4::from_vec3(&heatmap_color(t), 1.).to_pixel_data(1.))
+            }))
+        }
+        RenderPass::Edges => {
+            let geometry = render_pixels(settings, progress, &mut stats, |i, j| calc_geometry(ray_marcher, camera, settings, i, j))?;
+            let pixels = color_pixels(ray_marcher, camera, settings, Some(&geometry), progress, &mut stats)?;
+
+            let edges = &settings.edges;
+            let line = Vec4::from_vec3(&edges.color, 1.).to_pixel_data(ray_marcher.gamma);
+
+            DynamicImage::ImageRgb8(RgbImage::from_fn(settings.width, settings.height, |x, y| {
+                // the neighbors to the right and below, the pixels at the border compare with themselves
+                let here = &geometry[index(x, y)];
+                let right = &geometry[index((x + 1).min(settings.width - 1), y)];
+                let below = &geometry[index(x, (y + 1).min(settings.height - 1))];
+
+                if here.is_edge(right, edges) || here.is_edge(below, edges) {
+                    Rgb(line)
+                } else {
+                    Rgb(ray_marcher.post_processing(&pixels[index(x, y)].color).to_pixel_data(ray_marcher.gamma))
+                }
+            }))
+        }
+    };
+
+    Ok((image, stats))
+}
+
+// the shaded pixels, blocks the pre-pass found to be empty get the background without marching. the denoiser
+// uses the geometry of a pass that already has it, otherwise it's marched here
+fn color_pixels(
+    ray_marcher: &RayMarcher,
+    camera: &Camera,
+    settings: &RenderSettings,
+    geometry: Option<&[Geometry]>,
+    progress: &(dyn Fn(u32, u32) + Sync),
+    stats: &mut RenderStats,
+) -> Result<Vec<PixelColor>, String> {
+    let empty_blocks = settings.background_blocks.map(|block_size| find_empty_blocks(ray_marcher, camera, settings, block_size));
+
+    if let Some(empty_blocks) = &empty_blocks {
+        stats.background_blocks += empty_blocks.empty.len() as u64;
+        stats.skipped_blocks += empty_blocks.empty.iter().filter(|&&e| e).count() as u64;
+    }
+
+    let pixels = render_pixels(settings, progress, stats, |i, j| match &empty_blocks {
+        Some(empty_blocks) if empty_blocks.is_empty(i, j) => {
+            let color = ray_marcher.miss_color(&center_ray(camera, settings, i, j), ray_marcher.max_distance);
+            (PixelColor { color, hit_color: Vec3::zero() }, RenderStats::default())
+        }
+        _ => calc_pixel(ray_marcher, camera, settings, i, j),
+    })?;
+
+    let Some(denoise) = &settings.denoise else {
+        return Ok(pixels);
+    };
+
+    match geometry {
+        Some(geometry) => Ok(bilateral_filter(&pixels, geometry, settings, denoise)),
+        None => {
+            let geometry = render_pixels(settings, progress, stats, |i, j| calc_geometry(ray_marcher, camera, settings, i, j))?;
+            Ok(bilateral_filter(&pixels, &geometry, settings, denoise))
+        }
+    }
+}
+
+fn bilateral_filter(pixels: &[PixelColor], geometry: &[Geometry], settings: &RenderSettings, denoise: &DenoiseSettings) -> Vec<PixelColor> {
+    let width = settings.width as i64;
+    let height = settings.height as i64;
+    // the gaussian is close enough to 0 past 2 sigma
+    let radius = (denoise.spatial_sigma * 2.).ceil() as i64;
+
+    (0..pixels.len()).into_par_iter().map(|index| {
+        let x = index as i64 % width;
+        let y = index as i64 / width;
+        let center = &pixels[index];
+
+        let mut color = Vec4::zero();
+        let mut hit_color = Vec3::zero();
+        let mut total_weight = 0.;
+
+        for ny in (y - radius).max(0)..=(y + radius).min(height - 1) {
+            for nx in (x - radius).max(0)..=(x + radius).min(width - 1) {
+                let neighbor_index = (ny * width + nx) as usize;
+                let neighbor = &pixels[neighbor_index];
+                let distance_squared = ((nx - x) * (nx - x) + (ny - y) * (ny - y)) as Real;
+                let difference = Vec3::new(neighbor.color.x - center.color.x, neighbor.color.y - center.color.y, neighbor.color.z - center.color.z);
+
+                let weight = (-distance_squared / (2. * denoise.spatial_sigma * denoise.spatial_sigma)).exp()
+                    * (-difference.sqr_length() / (2. * denoise.range_sigma * denoise.range_sigma)).exp()
+                    * geometry[index].similarity(&geometry[neighbor_index], denoise);
+
+                color += neighbor.color * weight;
+                hit_color += neighbor.hit_color * weight;
+                total_weight += weight;
+            }
+        }
+
+        // the center always has a weight of 1
+        PixelColor {
+            color: color / total_weight,
+            hit_color: hit_color / total_weight,
+        }
+    }).collect()
+}
+
+// calculates every pixel in parallel tiles and returns them row by row from the top left, the stats of
+// the rays are added to stats. the pixel function gets the coordinates with j = 0 at the bottom
+fn render_pixels<T, F>(settings: &RenderSettings, progress: &(dyn Fn(u32, u32) + Sync), stats: &mut RenderStats, pixel: F) -> Result<Vec<T>, String>
+where
+    T: Copy + Default + Send + Sync,
+    F: Fn(u32, u32) -> (T, RenderStats) + Sync,
+{
+    let width = settings.width;
+    let height = settings.height;
+
+    let tiles = create_tiles(width, height);
+    let finished_tiles = AtomicU32::new(0);
+
+    // render the tiles in parallel, each tile is a small block of pixels which keeps the work local
+    let render_tiles = || -> Vec<Vec<(T, RenderStats)>> {
+        tiles.par_iter().map(|tile| -> Vec<(T, RenderStats)> {
+            // iterate over the pixels in the tile row by row and calculate their value
+            let tile_data = (tile.y..tile.y + tile.height)
+                .flat_map(|j| (tile.x..tile.x + tile.width).map(move |i| (i, j)))
+                .map(|(i, j)| pixel(i, j))
+                .collect();
+
+            progress(finished_tiles.fetch_add(1, Ordering::Relaxed) + 1, tiles.len() as u32);
+
+            tile_data
+        }).collect()
+    };
+
+    // use a dedicated thread pool when the number of threads is limited
+    let pixel_data = match settings.threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|err| format!("failed to create the render threads: {err}"))?
+            .install(render_tiles),
+        None => render_tiles(),
+    };
+
+    let pixel_stats = pixel_data.par_iter()
+        .flat_map(|tile_data| tile_data.par_iter().map(|(_, pixel_stats)| *pixel_stats))
+        .reduce(RenderStats::default, RenderStats::merge);
+    *stats = RenderStats::merge(*stats, pixel_stats);
+
+    // put the pixels of each tile at their place in the image, which has its origin at the top
+    let mut pixels = vec![T::default(); (width * height) as usize];
+    for (tile, tile_data) in tiles.iter().zip(pixel_data.iter()) {
+        for (index, (value, _)) in tile_data.iter().enumerate() {
+            let i = tile.x + index as u32 % tile.width;
+            let j = tile.y + index as u32 / tile.width;
+
+            pixels[((height - j - 1) * width + i) as usize] = *value;
+        }
+    }
+
+    Ok(pixels)
+}
+
+struct EmptyBlocks {
+    block_size: u32,
+    blocks_x: u32,
+    empty: Vec<bool>,
+}
+
+impl EmptyBlocks {
+    fn is_empty(&self, i: u32, j: u32) -> bool {
+        self.empty[((j / self.block_size) * self.blocks_x + i / self.block_size) as usize]
+    }
+}
+
+// marches one cone per block that contains the rays of all pixels in it, including the jitter of the samples
+fn find_empty_blocks(ray_marcher: &RayMarcher, camera: &Camera, settings: &RenderSettings, block_size: u32) -> EmptyBlocks {
+    let blocks_x = settings.width.div_ceil(block_size);
+    let blocks_y = settings.height.div_ceil(block_size);
+
+    // the pixels at the center of the image cover the biggest angle, so this holds for every block
+    let half_diagonal = (block_size as Real / 2. + 0.5) * real::consts::SQRT_2;
+    let angle = half_diagonal * camera.pixel_angle(settings.height);
+
+    let empty: Vec<bool> = (0..blocks_x * blocks_y).into_par_iter().map(|index| {
+        // rays of a lens with an aperture or of an orthographic camera don't share an origin, so they don't fit in a cone
+        if camera.aperture > 0. || camera.projection != Projection::Perspective {
+            return false;
+        }
+
+        let x = (index % blocks_x) * block_size;
+        let y = (index / blocks_x) * block_size;
+
+        // the blocks at the right and top border may be cut off
+        let center_i = x as Real + (block_size.min(settings.width - x) - 1) as Real / 2.;
+        let center_j = y as Real + (block_size.min(settings.height - y) - 1) as Real / 2.;
+
+        let u = center_i / ((settings.width - 1) as Real);
+        let v = center_j / ((settings.height - 1) as Real);
+
+        let mut rng = Rng::for_pixel(settings.seed, x, y, settings.frame);
+        ray_marcher.cone_is_empty(&camera.ray_for_pixel(u, v, settings.aspect_ratio(), &mut rng), angle)
+    }).collect();
+
+    EmptyBlocks {
+        block_size,
+        blocks_x,
+        empty,
+    }
+}
+
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+fn create_tiles(width: u32, height: u32) -> Vec<Tile> {
+    let mut tiles = Vec::new();
+
+    for y in (0..height).step_by(TILE_SIZE as usize) {
+        for x in (0..width).step_by(TILE_SIZE as usize) {
+            tiles.push(Tile {
+                x,
+                y,
+                // the tiles at the right and top border may be cut off
+                width: TILE_SIZE.min(width - x),
+                height: TILE_SIZE.min(height - y),
+            });
+        }
+    }
+
+    tiles
+}
+
+// linear colors of a pixel, tone mapping and gamma are applied when writing the image
+#[derive(Debug, Clone, Copy, Default)]
+struct PixelColor {
+    // average of all samples, w is the fraction of samples that hit a surface
+    color: Vec4,
+    // average of only the samples that hit a surface
+    hit_color: Vec3,
+}
+
+fn calc_pixel(rm: &RayMarcher, camera: &Camera, settings: &RenderSettings, i: u32, j: u32) -> (PixelColor, RenderStats) {
+    let mut rng = Rng::for_pixel(settings.seed, i, j, settings.frame);
+    let mut pixel_color = Vec4::zero();
+    let mut hit_color = Vec3::zero();
+    let mut pixel_stats = RenderStats::default();
+
+    // the pixel is split into a grid of strata with one jittered sample in each, so the samples can't clump
+    let columns = (settings.samples as Real).sqrt().ceil() as u32;
+    let rows = settings.samples.div_ceil(columns);
+
+    for sample in 0..settings.samples {
+        // a single sample stays in the pixel corner
+        let (du, dv) = if settings.samples > 1 {
+            let column = (sample % columns) as Real;
+            let row = (sample / columns) as Real;
+
+            ((column + rng.next_real()) / columns as Real - 0.5, (row + rng.next_real()) / rows as Real - 0.5)
+        } else {
+            (0., 0.)
+        };
+
+        let u = (i as Real + du) / ((settings.width - 1) as Real);
+        let v = (j as Real + dv) / ((settings.height - 1) as Real);
+
+        let r = camera.ray_for_pixel(u, v, settings.aspect_ratio(), &mut rng);
+        let (color, ray_stats) = rm.ray_marching(r, &mut rng);
+
+        pixel_color += color;
+        hit_color += Vec3::new(color.x, color.y, color.z) * color.w;
+        pixel_stats.add_ray(&ray_stats, rm.max_iterations);
+    }
+
+    if pixel_color.w > 0. {
+        hit_color = hit_color / pixel_color.w;
+    }
+
+    pixel_color = pixel_color / settings.samples as Real;
+
+    (PixelColor { color: pixel_color, hit_color }, pixel_stats)
+}
+
+// the analysis passes use a single ray through the pixel center, averaging jittered samples would blur
+// the values at edges into nonsense
+fn center_ray(camera: &Camera, settings: &RenderSettings, i: u32, j: u32) -> Ray {
+    let u = i as Real / ((settings.width - 1) as Real);
+    let v = j as Real / ((settings.height - 1) as Real);
+
+    let mut rng = Rng::for_pixel(settings.seed, i, j, settings.frame);
+    camera.ray_for_pixel(u, v, settings.aspect_ratio(), &mut rng)
+}
+
+// 16 bit grayscale, black at the camera and white at max_distance, misses are white as well
+fn calc_depth(rm: &RayMarcher, camera: &Camera, settings: &RenderSettings, i: u32, j: u32) -> (u16, RenderStats) {
+    let mut pixel_stats = RenderStats::default();
+
+    let ray_stats = rm.trace(&center_ray(camera, settings, i, j));
+    pixel_stats.add_ray(&ray_stats, rm.max_iterations);
+
+    let depth = (ray_stats.depth() / rm.max_distance).clamp(0., 1.);
+
+    ((depth * u16::MAX as Real).round() as u16, pixel_stats)
+}
+
+// world space normal encoded as normal * 0.5 + 0.5, misses are the encoded zero vector (gray)
+fn calc_normal(rm: &RayMarcher, camera: &Camera, settings: &RenderSettings, i: u32, j: u32) -> ([u8; 3], RenderStats) {
+    let mut pixel_stats = RenderStats::default();
+
+    let r = center_ray(camera, settings, i, j);
+    let ray_stats = rm.trace(&r);
+    pixel_stats.add_ray(&ray_stats, rm.max_iterations);
+
+    let normal = if ray_stats.hit {
+        let p = r.orig + r.dir * ray_stats.final_t;
+        rm.get_normal(&p, &r.dir)
+    } else {
+        Vec3::zero()
+    };
+
+    (Vec4::from_vec3(&(normal * 0.5 + 0.5), 1.).to_pixel_data(1.), pixel_stats)
+}
+
+// what the edge pass compares between neighboring pixels
+#[derive(Debug, Clone, Copy, Default)]
+struct Geometry {
+    hit: bool,
+    depth: Real,
+    normal: Vec3,
+}
+
+impl Geometry {
+    // silhouettes are where only one of the pixels hits or the depth jumps, creases are where the normal turns
+    fn is_edge(&self, other: &Geometry, edges: &EdgeSettings) -> bool {
+        if self.hit != other.hit {
+            return true;
+        }
+
+        if !self.hit {
+            return false;
+        }
+
+        let depth_difference = (self.depth - other.depth).abs() / self.depth.min(other.depth).max(Real::EPSILON);
+
+        depth_difference > edges.depth_threshold || 1. - Vec3::dot(&self.normal, &other.normal) > edges.normal_threshold
+    }
+
+    // the weight of a neighbor in the denoiser, 1 for the same surface point falling off with the relative
+    // depth difference and the angle between the normals. a hit and a miss don't mix at all
+    fn similarity(&self, other: &Geometry, denoise: &DenoiseSettings) -> Real {
+        if self.hit != other.hit {
+            return 0.;
+        }
+
+        if !self.hit {
+            return 1.;
+        }
+
+        let depth_difference = (self.depth - other.depth) / self.depth.min(other.depth).max(Real::EPSILON);
+        let facing = Vec3::dot(&self.normal, &other.normal).max(0.);
+
+        (-depth_difference * depth_difference / (2. * denoise.depth_sigma * denoise.depth_sigma)).exp() * facing.powf(denoise.normal_exponent)
+    }
+}
+
+fn calc_geometry(rm: &RayMarcher, camera: &Camera, settings: &RenderSettings, i: u32, j: u32) -> (Geometry, RenderStats) {
+    let mut pixel_stats = RenderStats::default();
+
+    let r = center_ray(camera, settings, i, j);
+    let ray_stats = rm.trace(&r);
+    pixel_stats.add_ray(&ray_stats, rm.max_iterations);
+
+    if !ray_stats.hit {
+        return (Geometry::default(), pixel_stats);
+    }
+
+    let p = r.orig + r.dir * ray_stats.final_t;
+
+    (Geometry { hit: true, depth: ray_stats.depth(), normal: rm.get_normal(&p, &r.dir) }, pixel_stats)
+}
+
+fn calc_iterations(rm: &RayMarcher, camera: &Camera, settings: &RenderSettings, i: u32, j: u32) -> (i32, RenderStats) {
+    let mut pixel_stats = RenderStats::default();
+
+    let ray_stats = rm.trace(&center_ray(camera, settings, i, j));
+    pixel_stats.add_ray(&ray_stats, rm.max_iterations);
+
+    (ray_stats.iterations, pixel_stats)
+}
+
+// blue -> cyan -> green -> yellow -> red for t in [0, 1]
+fn heatmap_color(t: Real) -> Vec3 {
+    let stops = [
+        Vec3::new(0., 0., 1.),
+        Vec3::new(0., 1., 1.),
+        Vec3::new(0., 1., 0.),
+        Vec3::new(1., 1., 0.),
+        Vec3::new(1., 0., 0.),
+    ];
+
+    let scaled = t.clamp(0., 1.) * (stops.len() - 1) as Real;
+    let index = (scaled as usize).min(stops.len() - 2);
+
+    Vec3::lerp(&stops[index], &stops[index + 1], scaled - index as Real)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::camera::create_camera;
+    use crate::distance_fields::{build_bvh, DistanceField, Sphere};
+    use crate::ray_marching::{create_ray_marcher, RayMarcherBuilder};
+    use crate::scene::SceneFile;
+
+    fn sphere_setup() -> (RayMarcher, Camera, RenderSettings) {
+        let sphere = DistanceField::Sphere(Sphere {
+            pos: Vec3::new(0., 0., 0.),
+            size: 1.,
+        });
+
+        let camera = create_camera(Vec3::new(0., 0., -5.), Vec3::new(0., 0., 0.));
+
+        let settings = RenderSettings {
+            width: 32,
+            height: 24,
+            threads: Some(2),
+            ..RenderSettings::default()
+        };
+
+        (create_ray_marcher(sphere), camera, settings)
+    }
+
+    #[test]
+    fn render_returns_the_image_and_the_stats() {
+        let (ray_marcher, camera, settings) = sphere_setup();
+
+        let (image, stats) = render(&ray_marcher, &camera, &settings).unwrap();
+
+        assert_eq!(image.dimensions(), (32, 24));
+        assert_eq!(stats.rays, 32 * 24);
+        assert!(stats.hits > 0 && stats.hits < stats.rays);
+
+        // the sphere fills the center, the corners see the background
+        assert_ne!(image.get_pixel(16, 12), image.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn render_reports_the_progress_of_every_tile() {
+        let (ray_marcher, camera, settings) = sphere_setup();
+        let last = Mutex::new((0, 0));
+
+        render_pass(&ray_marcher, &camera, &settings, RenderPass::Color, OutputFormat::Png, false, &|finished, total| {
+            let mut last = last.lock().unwrap();
+            *last = (last.0.max(finished), total);
+        })
+        .unwrap();
+
+        let (finished, total) = *last.lock().unwrap();
+        assert!(total > 0);
+        assert_eq!(finished, total);
+    }
+
+    #[test]
+    fn render_returns_settings_errors() {
+        let (ray_marcher, camera, mut settings) = sphere_setup();
+        settings.threads = Some(0);
+
+        assert!(render(&ray_marcher, &camera, &settings).is_err());
+    }
+
+    fn reference_setup() -> (RayMarcher, Camera, RenderSettings) {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/sphere_on_checker_plane.json");
+        let scene_file = SceneFile::load(path).unwrap();
+
+        let ray_marcher = RayMarcherBuilder::new(build_bvh(scene_file.scene))
+            .lights(scene_file.lights)
+            .build();
+
+        let settings = RenderSettings {
+            width: 96,
+            height: 54,
+            ..scene_file.settings
+        };
+
+        (ray_marcher, scene_file.camera, settings)
+    }
+
+    fn reference_image() -> RgbImage {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/reference/sphere_on_checker_plane.png");
+        image::open(path).unwrap().to_rgb8()
+    }
+
+    // the reference was rendered by the default scalar build, the simd build has to match it byte for byte
+    #[cfg(not(feature = "f32"))]
+    #[test]
+    fn render_matches_the_reference_image() {
+        let (ray_marcher, camera, settings) = reference_setup();
+
+        let (image, _) = render(&ray_marcher, &camera, &settings).unwrap();
+
+        assert_eq!(image.as_raw(), reference_image().as_raw());
+    }
+
+    // f32 can't hit the same pixels, on average a channel may be off by less than one step and only
+    // pixels on the silhouettes and checker edges may flip completely
+    #[cfg(feature = "f32")]
+    #[test]
+    fn f32_render_stays_close_to_the_reference_image() {
+        let (ray_marcher, camera, settings) = reference_setup();
+
+        let (image, _) = render(&ray_marcher, &camera, &settings).unwrap();
+        let reference = reference_image();
+
+        let differences: Vec<u32> = image.as_raw().iter().zip(reference.as_raw()).map(|(a, b)| a.abs_diff(*b) as u32).collect();
+        let mean = differences.iter().sum::<u32>() as Real / differences.len() as Real;
+        let far_off = differences.iter().filter(|difference| **difference > 16).count() as Real / differences.len() as Real;
+
+        assert!(mean < 1., "mean difference {mean}");
+        assert!(far_off < 0.01, "{far_off} of the channels are far off");
+    }
+
+    #[test]
+    fn renders_with_the_same_seed_are_identical() {
+        let (ray_marcher, mut camera, mut settings) = sphere_setup();
+        // jittered samples and a lens both draw from the rng
+        settings.samples = 4;
+        settings.seed = 7;
+        camera.aperture = 0.2;
+
+        let (first, _) = render(&ray_marcher, &camera, &settings).unwrap();
+        let (second, _) = render(&ray_marcher, &camera, &settings).unwrap();
+        assert_eq!(first.as_raw(), second.as_raw());
+
+        settings.seed = 8;
+        let (other, _) = render(&ray_marcher, &camera, &settings).unwrap();
+        assert_ne!(first.as_raw(), other.as_raw());
+    }
+
+    #[test]
+    fn tiled_pixels_match_a_row_by_row_render() {
+        let (ray_marcher, camera, mut settings) = reference_setup();
+        // not a multiple of the tile size, so the border tiles are cut off
+        settings.width = 2 * TILE_SIZE + 7;
+        settings.height = TILE_SIZE + 13;
+        settings.samples = 2;
+
+        let pixel = |i, j| calc_pixel(&ray_marcher, &camera, &settings, i, j);
+
+        let tiled = render_pixels(&settings, &|_, _| {}, &mut RenderStats::default(), pixel).unwrap();
+
+        // the row based order of the renderer before the tiles, from the top row down
+        let rows: Vec<PixelColor> = (0..settings.height)
+            .rev()
+            .flat_map(|j| (0..settings.width).map(move |i| (i, j)))
+            .map(|(i, j)| pixel(i, j).0)
+            .collect();
+
+        let channels = |pixels: &[PixelColor]| -> Vec<[Real; 7]> {
+            pixels.iter().map(|p| [p.color.x, p.color.y, p.color.z, p.color.w, p.hit_color.x, p.hit_color.y, p.hit_color.z]).collect()
+        };
+        assert_eq!(channels(&tiled), channels(&rows));
+    }
+
+    // a 16x8 image, the left half on a surface at depth 1 facing the camera and the right half on the given geometry
+    fn split_buffers(right: Geometry, left_color: Vec3, right_color: Vec3) -> (RenderSettings, Vec<PixelColor>, Vec<Geometry>) {
+        let settings = RenderSettings { width: 16, height: 8, denoise: Some(DenoiseSettings { range_sigma: 10., ..DenoiseSettings::default() }), ..RenderSettings::default() };
+        let left = Geometry { hit: true, depth: 1., normal: Vec3::new(0., 0., -1.) };
+
+        let (pixels, geometry) = (0..16 * 8)
+            .map(|index| {
+                let color = if index % 16 < 8 { left_color } else { right_color };
+                let pixel = PixelColor { color: Vec4::from_vec3(&color, 1.), hit_color: color };
+                (pixel, if index % 16 < 8 { left } else { right })
+            })
+            .unzip();
+
+        (settings, pixels, geometry)
+    }
+
+    #[test]
+    fn denoise_does_not_blur_across_a_depth_jump() {
+        // the color weight is wide open, only the geometry keeps the sides apart
+        let behind = Geometry { hit: true, depth: 2., normal: Vec3::new(0., 0., -1.) };
+        let (settings, pixels, geometry) = split_buffers(behind, Vec3::new(1., 0., 0.), Vec3::new(0., 0., 1.));
+
+        let filtered = bilateral_filter(&pixels, &geometry, &settings, settings.denoise.as_ref().unwrap());
+
+        // the pixels right next to the border
+        assert!(filtered[3 * 16 + 7].hit_color.approx_eq(&Vec3::new(1., 0., 0.), 1e-4));
+        assert!(filtered[3 * 16 + 8].hit_color.approx_eq(&Vec3::new(0., 0., 1.), 1e-4));
+    }
+
+    #[test]
+    fn denoise_does_not_blur_across_a_crease() {
+        let wall = Geometry { hit: true, depth: 1., normal: Vec3::new(1., 0., 0.) };
+        let (settings, pixels, geometry) = split_buffers(wall, Vec3::new(1., 0., 0.), Vec3::new(0., 0., 1.));
+
+        let filtered = bilateral_filter(&pixels, &geometry, &settings, settings.denoise.as_ref().unwrap());
+
+        assert!(filtered[3 * 16 + 7].hit_color.approx_eq(&Vec3::new(1., 0., 0.), 1e-4));
+        assert!(filtered[3 * 16 + 8].hit_color.approx_eq(&Vec3::new(0., 0., 1.), 1e-4));
+    }
+
+    #[test]
+    fn denoise_blends_neighbors_on_the_same_surface() {
+        let same = Geometry { hit: true, depth: 1., normal: Vec3::new(0., 0., -1.) };
+        let (settings, pixels, geometry) = split_buffers(same, Vec3::new(1., 0., 0.), Vec3::new(0., 0., 1.));
+
+        let filtered = bilateral_filter(&pixels, &geometry, &settings, settings.denoise.as_ref().unwrap());
+
+        let border = filtered[3 * 16 + 7].hit_color;
+        assert!(border.x < 0.9 && border.z > 0.1, "{border:?}");
+    }
+
+    #[test]
+    fn edges_pass_marches_the_geometry_once_with_denoise() {
+        let (ray_marcher, camera, mut settings) = sphere_setup();
+        let (_, plain) = render_pass(&ray_marcher, &camera, &settings, RenderPass::Edges, OutputFormat::Png, false, &|_, _| {}).unwrap();
+
+        settings.denoise = Some(DenoiseSettings::default());
+        let (_, denoised) = render_pass(&ray_marcher, &camera, &settings, RenderPass::Edges, OutputFormat::Png, false, &|_, _| {}).unwrap();
+
+        assert_eq!(plain.rays, denoised.rays);
+    }
+
+    #[test]
+    fn panoramic_renders_need_an_aspect_ratio_of_2_to_1() {
+        let (ray_marcher, mut camera, mut settings) = sphere_setup();
+        camera.projection = Projection::Panoramic;
+
+        assert!(render(&ray_marcher, &camera, &settings).is_err());
+        assert!(settings.validate_for(&camera).is_err());
+
+        settings.width = 2 * settings.height;
+        assert!(settings.validate_for(&camera).is_ok());
+        assert!(render(&ray_marcher, &camera, &settings).is_ok());
+
+        // other projections take any size
+        camera.projection = Projection::Perspective;
+        settings.width = settings.height + 1;
+        assert!(settings.validate_for(&camera).is_ok());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::real::Real;
+
+// xorshift64* https://en.wikipedia.org/wiki/Xorshift, small enough to create one for every pixel so the
+// threads don't share any state and the samples of a pixel are the same on every run
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // xorshift is stuck at 0 and similar seeds need to end up far apart
+        let state = splitmix64(seed);
+
+        Rng {
+            state: if state == 0 { 0x9e3779b97f4a7c15 } else { state },
+        }
+    }
+
+    // an independent sequence for every pixel of every frame
+    pub fn for_pixel(seed: u64, i: u32, j: u32, frame: u32) -> Rng {
+        let mut h = splitmix64(seed);
+        h = splitmix64(h ^ i as u64);
+        h = splitmix64(h ^ j as u64);
+        h = splitmix64(h ^ frame as u64);
+
+        Rng::new(h)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+
+        self.state.wrapping_mul(0x2545f4914f6cdd1d)
+    }
+
+    // uniform in [0, 1), 24 bits fit into the mantissa of f32 and f64 without rounding up to 1
+    pub fn next_real(&mut self) -> Real {
+        (self.next_u64() >> 40) as Real / (1u64 << 24) as Real
+    }
+
+    // uniform in [min, max)
+    pub fn range(&mut self, min: Real, max: Real) -> Real {
+        min + (max - min) * self.next_real()
+    }
+
+    pub fn in_unit_disk(&mut self) -> (Real, Real) {
+        loop {
+            let x = self.range(-1., 1.);
+            let y = self.range(-1., 1.);
+
+            if x * x + y * y < 1. {
+                return (x, y);
+            }
+        }
+    }
+}
+
+// https://prng.di.unimi.it/splitmix64.c, scrambles the bits of the seeds
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        let mut a = Rng::for_pixel(3, 10, 20, 0);
+        let mut b = Rng::for_pixel(3, 10, 20, 0);
+        let mut other = Rng::for_pixel(3, 10, 20, 1);
+
+        let first: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        let third: Vec<u64> = (0..8).map(|_| other.next_u64()).collect();
+
+        assert_eq!(first, second);
+        assert_ne!(first, third);
+    }
+
+    #[test]
+    fn reals_stay_in_their_range() {
+        let mut rng = Rng::new(0);
+
+        for _ in 0..10000 {
+            let r = rng.next_real();
+            assert!((0. ..1.).contains(&r));
+
+            let (x, y) = rng.in_unit_disk();
+            assert!(x * x + y * y < 1.);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeOwned;
+
+use crate::camera::Camera;
+use crate::distance_fields::DistanceField;
+use crate::light::Light;
+use crate::render::RenderSettings;
+
+// a complete render setup, everything needed to render an image
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub scene: DistanceField,
+    pub camera: Camera,
+    pub lights: Vec<Light>,
+    pub settings: RenderSettings,
+}
+
+impl SceneFile {
+    pub fn load(path: &str) -> Result<SceneFile, String> {
+        read_json(path)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        write_json(self, path)
+    }
+}
+
+// reads a distance field tree from a json file, see scenes/ for examples
+pub fn load_scene(path: &str) -> Result<DistanceField, String> {
+    read_json(path)
+}
+
+pub fn save_scene(scene: &DistanceField, path: &str) -> Result<(), String> {
+    write_json(scene, path)
+}
+
+fn read_json<T: DeserializeOwned>(path: &str) -> Result<T, String> {
+    let json = fs::read_to_string(path).map_err(|err| format!("failed to read scene {path}: {err}"))?;
+
+    serde_json::from_str(&json).map_err(|err| format!("failed to parse scene {path}: {err}"))
+}
+
+fn write_json<T: Serialize>(value: &T, path: &str) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|err| format!("failed to serialize scene: {err}"))?;
+
+    fs::write(path, json).map_err(|err| format!("failed to write scene {path}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene_path(name: &str) -> String {
+        format!("{}/scenes/{name}", env!("CARGO_MANIFEST_DIR"))
+    }
+
+    #[test]
+    fn scene_round_trips_through_json() {
+        let scene = load_scene(&scene_path("julia.json")).unwrap();
+        let json = serde_json::to_string(&scene).unwrap();
+        let reloaded: DistanceField = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(serde_json::to_string(&reloaded).unwrap(), json);
+
+        let path = std::env::temp_dir().join("sphere_tracer_scene_round_trip.json");
+        save_scene(&scene, path.to_str().unwrap()).unwrap();
+        assert_eq!(serde_json::to_string(&load_scene(path.to_str().unwrap()).unwrap()).unwrap(), json);
+    }
+
+    #[test]
+    fn scene_file_round_trips_through_json() {
+        let scene_file = SceneFile::load(&scene_path("glass_sphere.json")).unwrap();
+
+        let path = std::env::temp_dir().join("sphere_tracer_scene_file_round_trip.json");
+        scene_file.save(path.to_str().unwrap()).unwrap();
+        let reloaded = SceneFile::load(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(serde_json::to_string(&reloaded).unwrap(), serde_json::to_string(&scene_file).unwrap());
+        assert_eq!(reloaded.camera.origin.y, 0.5);
+        assert_eq!(reloaded.settings.width, 640);
+    }
+
+    #[test]
+    fn example_scene_files_load() {
+        for name in ["glass_sphere.json", "julia_setup.json", "sphere_ground.json", "sphere_on_checker_plane.json", "terrain.json"] {
+            assert!(SceneFile::load(&scene_path(name)).is_ok(), "{name}");
+        }
+    }
+}